@@ -0,0 +1,120 @@
+//! Semantic description of the UI tree.
+//!
+//! There is no assistive technology backend yet, but the tree allows asserting on the meaning of
+//! the UI rather than its layout, e.g; "a button labeled Submit exists".
+use flax::{Entity, World};
+
+use crate::{
+    components::{aria_label, checked, disabled, role, selected},
+    fragment::children_of,
+};
+
+/// The semantic role of a widget
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role {
+    /// A widget without any specific meaning, such as a layout container
+    #[default]
+    Generic,
+    Button,
+    Checkbox,
+    Label,
+    TextInput,
+    List,
+    ListItem,
+    ProgressBar,
+    Tab,
+    TabList,
+}
+
+/// A node in the accessibility tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessibilityNode {
+    pub id: Entity,
+    pub role: Role,
+    pub label: Option<String>,
+    pub disabled: bool,
+    pub checked: bool,
+    pub selected: bool,
+    pub children: Vec<AccessibilityNode>,
+}
+
+impl AccessibilityNode {
+    /// Returns an iterator over this node and all its descendants, depth first
+    pub fn iter(&self) -> impl Iterator<Item = &AccessibilityNode> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+
+    /// Finds the first node with the given role and label
+    pub fn find(&self, role: Role, label: &str) -> Option<&AccessibilityNode> {
+        self.iter()
+            .find(|node| node.role == role && node.label.as_deref() == Some(label))
+    }
+}
+
+/// Builds the accessibility tree of the fragment `root` and its descendants
+pub fn tree(world: &World, root: Entity) -> AccessibilityNode {
+    let entity = world.entity(root).unwrap();
+
+    AccessibilityNode {
+        id: root,
+        role: entity.get(role()).map(|v| *v).unwrap_or_default(),
+        label: entity.get(aria_label()).ok().map(|v| v.clone()),
+        disabled: entity.has(disabled()),
+        checked: entity.has(checked()),
+        selected: entity.has(selected()),
+        children: children_of(world, root)
+            .into_iter()
+            .map(|child| tree(world, child))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use futures::FutureExt;
+
+    use crate::{app::App, Fragment, Widget};
+
+    use super::*;
+
+    struct Button(&'static str);
+
+    #[async_trait]
+    impl Widget for Button {
+        type Output = ();
+
+        async fn mount(self, mut fragment: Fragment) {
+            fragment.set_role(Role::Button).set_label(self.0);
+        }
+    }
+
+    #[test]
+    fn labeled_tree() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        root.set_label("Form");
+
+        let cancel = root.attach(Button("Cancel"));
+        let submit = root.attach(Button("Submit"));
+        let id = submit.id();
+        assert!(cancel.now_or_never().is_some());
+        assert!(submit.now_or_never().is_some());
+
+        let tree = tree(&handle.world(), root.id());
+
+        assert_eq!(tree.role, Role::Generic);
+        assert_eq!(tree.label.as_deref(), Some("Form"));
+        assert_eq!(tree.children.len(), 2);
+
+        let button = tree.find(Role::Button, "Submit").unwrap();
+        assert_eq!(button.id, id);
+        assert!(tree.find(Role::Label, "Submit").is_none());
+    }
+}
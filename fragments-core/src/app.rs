@@ -1,14 +1,48 @@
 use std::{
-    iter::once,
-    sync::{Arc, Mutex, MutexGuard},
+    any::{type_name, Any},
+    collections::{BTreeSet, VecDeque},
+    convert::Infallible,
+    iter, mem,
+    pin::{pin, Pin},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, Weak,
+    },
+    task::{Context, Poll, Waker},
 };
 
-use flax::{Entity, World};
+use futures::{
+    future::{poll_fn, BoxFuture, Fuse, LocalBoxFuture},
+    Future, FutureExt, Stream,
+};
+
+use flax::{
+    buffer::ComponentBuffer,
+    child_of, component, entity_ids,
+    events::{ChangeSubscriber, SubscriberFilterExt},
+    Component, ComponentKey, ComponentValue, Entity, Query, StaticFilter, World,
+};
 use flume::{Receiver, Sender};
+use glam::Vec2;
+use serde::{de::DeserializeOwned, Serialize};
 
 use slotmap::new_key_type;
+use tokio::{
+    sync::Notify,
+    task::{JoinHandle, LocalSet},
+};
 
-use crate::{Fragment, Widget};
+use crate::{
+    components::{
+        aria_label, canvas, checked, content, disabled, headless, order, persistent, position,
+        role, selected, size, viewport, widget,
+    },
+    fragment::{children_of, name_path},
+    lifecycle::Lifecycle,
+    notify::{self, NotifyReceiver, SubscriptionGuard},
+    style::{apply_stylesheet, Stylesheet},
+    Fragment, Widget,
+};
 
 new_key_type! {
     struct EffectKey;
@@ -20,50 +54,381 @@ pub struct App {
     world: Arc<Mutex<World>>,
     rx: Receiver<Event>,
     tx: Sender<Event>,
+    priority_rx: Receiver<Event>,
+    priority_tx: Sender<Event>,
+    roots: (Sender<BoxedRoot>, Receiver<BoxedRoot>),
+    viewport: Option<Vec2>,
+    lifecycle: Arc<Lifecycle>,
+    pause: Arc<Pause>,
+    max_depth: Option<usize>,
+    ctrlc_exit: bool,
+    stylesheet: Option<Arc<Stylesheet>>,
+    finalizers: Finalizers,
+    user_handlers: UserHandlers,
+}
+
+/// Returns whether the event was of the handler's type
+type UserHandler = Box<dyn FnMut(&mut World, &(dyn Any + Send)) -> bool + Send>;
+
+/// See [`App::on_user_event`]
+#[derive(Default)]
+struct UserHandlers(Vec<UserHandler>);
+
+impl std::fmt::Debug for UserHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("UserHandlers").field(&self.0.len()).finish()
+    }
+}
+
+type Finalizer = Box<dyn FnOnce(AppRef) -> BoxFuture<'static, ()> + Send>;
+
+/// See [`App::on_exit`]
+#[derive(Default)]
+struct Finalizers(Vec<Finalizer>);
+
+impl std::fmt::Debug for Finalizers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Finalizers").field(&self.0.len()).finish()
+    }
 }
 
 impl App {
     pub fn new() -> Self {
         let (tx, rx) = flume::unbounded();
+        let (priority_tx, priority_rx) = flume::unbounded();
         Self {
             world: Default::default(),
             rx,
             tx,
+            priority_rx,
+            priority_tx,
+            roots: flume::unbounded(),
+            viewport: None,
+            lifecycle: Default::default(),
+            pause: Default::default(),
+            max_depth: None,
+            ctrlc_exit: false,
+            stylesheet: None,
+            finalizers: Default::default(),
+            user_handlers: Default::default(),
         }
     }
 
-    /// Runs the app until the root exits
-    pub async fn run<W: Widget>(self, root: W) -> W::Output {
-        let rx = self.rx;
+    /// Runs the app headless, with a fixed viewport instead of a real terminal.
+    ///
+    /// The root fragment gets the [`viewport`] and [`headless`] components, which makes the
+    /// terminal output target an in-memory grid. See [`crate::terminal::render_to_string`].
+    pub fn with_viewport(mut self, viewport: Vec2) -> Self {
+        self.viewport = Some(viewport);
+        self
+    }
 
-        let handle = AppRef {
-            world: self.world.clone(),
-            tx: self.tx,
+    /// Limits how deeply fragments can be nested below the root, which is unlimited by default.
+    ///
+    /// Widgets attached beyond the limit are not mounted and their fragment is despawned, and an
+    /// error is logged instead. This stops a widget which unconditionally attaches itself from
+    /// spawning entities until the process runs out of memory.
+    ///
+    /// As there is no output to return, the future returned by [`Fragment::attach`] for such a
+    /// widget never completes, and neither does a parent awaiting it. Use
+    /// [`Fragment::try_attach`] to handle the limit instead.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Styles widgets through `stylesheet`, which is applied each time the event loop has
+    /// processed a batch of events, and whenever the [`class`](crate::components::class) or a
+    /// state selected by the stylesheet changes. See [`apply_stylesheet`].
+    pub fn with_stylesheet(mut self, stylesheet: Stylesheet) -> Self {
+        self.stylesheet = Some(Arc::new(stylesheet));
+        self
+    }
+
+    /// Exits the app when the process is interrupted, such as by Ctrl-C in a terminal which is not
+    /// in raw mode.
+    ///
+    /// The exit goes through [`Event::Exit`], so the root is dropped as usual, which lets widgets
+    /// such as renderers restore the terminal.
+    pub fn with_ctrlc_exit(mut self) -> Self {
+        self.ctrlc_exit = true;
+        self
+    }
+
+    /// Handles the application's own events of type `E`, sent through [`Event::user`].
+    ///
+    /// The handler is invoked by the event loop with the world locked, in order with the other
+    /// events. Several handlers may be registered for the same type, and events without any are
+    /// logged and dropped.
+    pub fn on_user_event<E: 'static>(
+        &mut self,
+        mut handler: impl FnMut(&mut World, &E) + Send + 'static,
+    ) {
+        self.user_handlers
+            .0
+            .push(Box::new(move |world, event: &(dyn Any + Send)| {
+                let Some(event) = event.downcast_ref::<E>() else {
+                    return false;
+                };
+                handler(world, event);
+                true
+            }));
+    }
+
+    /// Registers an async cleanup, such as flushing logs, to run when the app exits.
+    ///
+    /// Finalizers run in registration order once the tree has been torn down, and [`App::run`]
+    /// returns after all of them completed.
+    pub fn on_exit(&mut self, f: impl FnOnce(AppRef) -> BoxFuture<'static, ()> + Send + 'static) {
+        self.finalizers.0.push(Box::new(f));
+    }
+
+    /// Runs the app until the root completes or an exit is requested.
+    ///
+    /// Returns the exit code, which is either the code requested through [`Event::ExitWith`] or
+    /// the output of the root.
+    ///
+    /// A root whose output is a `Result` fails the app: once it returns an `Err`, the event loop
+    /// stops and the finalizers run, after which the error is returned.
+    ///
+    /// The root is driven on a [`LocalSet`], which allows it to use [`AppRef::spawn_local`].
+    pub async fn run<W>(mut self, root: W) -> eyre::Result<i32>
+    where
+        W: Widget,
+        W::Output: IntoExitCode,
+    {
+        let headless_viewport = self.viewport;
+        let ctrlc_exit = self.ctrlc_exit;
+        let rx = self.rx.clone();
+        let priority_rx = self.priority_rx.clone();
+        let roots = self.roots.1.clone();
+        let finalizers = mem::take(&mut self.finalizers);
+        let (handle, handle_events) = self.start();
+
+        let exit_on_ctrlc = async {
+            if ctrlc_exit && tokio::signal::ctrl_c().await.is_ok() {
+                handle.enqueue(Event::Exit).ok();
+            }
+
+            std::future::pending::<Infallible>().await
         };
 
+        let init_root = |state: &mut Fragment| {
+            if let Some(size) = headless_viewport {
+                state.write().set(viewport(), size).set(headless(), ());
+            }
+        };
+
+        let mut state = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        init_root(&mut state);
+        let root_fragment = state.duplicate();
+        let mut root: LocalBoxFuture<eyre::Result<i32>> = Box::pin(
+            handle
+                .pause
+                .clone()
+                .gate(root.mount(state))
+                .map(IntoExitCode::into_exit_code),
+        );
+
+        let local = LocalSet::new();
+        let code = local
+            .run_until(async {
+                tokio::pin!(handle_events, exit_on_ctrlc);
+                loop {
+                    tokio::select! {
+                        code = &mut handle_events => break code,
+                        output = &mut root => break output,
+                        Ok(widget) = roots.recv_async() => {
+                            // Dropping the old root despawns the widgets it was still running
+                            drop(mem::replace(&mut root, Box::pin(std::future::pending())));
+
+                            let mut state = root_fragment.duplicate();
+                            state.write().clear();
+                            init_root(&mut state);
+
+                            root = Box::pin(
+                                handle
+                                    .pause
+                                    .clone()
+                                    .gate(widget.mount(state))
+                                    .map(IntoExitCode::into_exit_code),
+                            );
+                        }
+                        never = &mut exit_on_ctrlc => match never {},
+                    }
+                }
+            })
+            .await;
+
+        // Dropping the root despawns the widgets which were still running
+        drop(root);
         {
-            let world = self.world.clone();
-            let handle_events = async move {
-                while let Ok(event) = rx.recv_async().await {
-                    let mut world = world.lock().unwrap();
-                    for event in once(event).chain(rx.drain()) {
-                        println!("Handling event: {event:?}");
-                        match event {
-                            Event::Exit => return Ok(()),
-                            Event::Despawn(id) => {
-                                world.despawn(id)?;
+            let world = handle.world();
+            for event in priority_rx.drain().chain(rx.drain()) {
+                if let Event::Despawn(id) = event {
+                    handle.lifecycle.unmount(&world, id);
+                }
+            }
+
+            let leaked = handle.lifecycle.leaked(&world);
+            if !leaked.is_empty() {
+                tracing::warn!(?leaked, "Fragments were mounted but never unmounted");
+            }
+        }
+
+        for finalizer in finalizers.0 {
+            finalizer(handle.clone()).await;
+        }
+
+        code
+    }
+
+    /// Returns a handle to the app along with the future which processes the app's events.
+    ///
+    /// The future completes with the exit code when [`Event::Exit`] or [`Event::ExitWith`] is
+    /// received.
+    ///
+    /// Once every [`AppRef`] is dropped nothing can enqueue events anymore, so the remaining
+    /// events are processed and the future completes with 0. Weak handles, such as those held by
+    /// event hooks, do not keep the event loop running.
+    ///
+    /// Before each event, all pending events of the priority queue are handled.
+    /// See [`AppRef::enqueue_priority`].
+    pub(crate) fn start(self) -> (AppRef, impl Future<Output = eyre::Result<i32>>) {
+        let handle = self.handle();
+        let Self {
+            world,
+            rx,
+            priority_rx,
+            lifecycle,
+            pause,
+            stylesheet,
+            mut user_handlers,
+            ..
+        } = self;
+
+        // Restyles widgets whose class or state changed outside of the event loop, such as
+        // through `set_focus`
+        let restyle = Arc::new(Notify::new());
+        if let Some(stylesheet) = &stylesheet {
+            world.lock().unwrap().subscribe(
+                ChangeSubscriber::new(&stylesheet.selector_keys(), Arc::downgrade(&restyle))
+                    .filter(widget().with()),
+            );
+        }
+
+        let handle_events = async move {
+            let next_event = || priority_rx.try_recv().or_else(|_| rx.try_recv()).ok();
+
+            loop {
+                // Nothing is left to restyle once the queues are done
+                let mut restyled = pin!(if rx.is_disconnected() && priority_rx.is_disconnected() {
+                    Fuse::terminated()
+                } else {
+                    restyle.notified().fuse()
+                });
+
+                // Queues which are disconnected and empty are skipped
+                let event = futures::select_biased! {
+                    event = priority_rx.recv_async() => event.map(Some),
+                    event = rx.recv_async() => event.map(Some),
+                    _ = restyled => Ok(None),
+                    complete => Err(flume::RecvError::Disconnected),
+                };
+
+                let Ok(event) = event else {
+                    tracing::debug!("All app handles were dropped");
+                    break;
+                };
+
+                poll_fn(|cx| pause.poll_resumed(cx)).await;
+
+                let _span = tracing::debug_span!("events").entered();
+
+                let mut exit = None;
+                let mut despawned = Vec::new();
+
+                let mut world = world.lock().unwrap();
+                for event in event.into_iter().chain(iter::from_fn(next_event)) {
+                    let _span = tracing::trace_span!("event").entered();
+                    tracing::trace!(?event, "handling event");
+                    match event {
+                        Event::Exit => {
+                            exit = Some(0);
+                            break;
+                        }
+                        Event::ExitWith(code) => {
+                            exit = Some(code);
+                            break;
+                        }
+                        Event::Despawn(id) => {
+                            // Already despawned along with a cleared parent, or by an earlier
+                            // event of the batch
+                            if !world.is_alive(id) {
+                                continue;
+                            }
+
+                            take_despawn_callbacks(&mut world, id, &mut despawned);
+                            lifecycle.unmount(&world, id);
+                            world.despawn_recursive(id, child_of)?;
+                        }
+                        Event::SetComponents(id, mut components) => {
+                            // The entity may have been despawned after the event was sent
+                            if world.is_alive(id) {
+                                world.set_with(id, &mut components)?;
+                            }
+                        }
+                        Event::User(name, event) => {
+                            let mut handled = false;
+                            for handler in &mut user_handlers.0 {
+                                handled |= handler(&mut world, &*event);
+                            }
+
+                            if !handled {
+                                tracing::warn!(event = name, "No handler for user event");
                             }
                         }
                     }
                 }
 
-                Ok::<_, eyre::Report>(())
-            };
-            tokio::spawn(handle_events);
-        }
+                if let Some(stylesheet) = &stylesheet {
+                    apply_stylesheet(&mut world, stylesheet);
+                    // Changes made by the events are covered already
+                    restyle.notified().now_or_never();
+                }
+
+                // Callbacks are free to use the world
+                drop(world);
+                for (id, callback) in despawned {
+                    callback(id)
+                }
+
+                if let Some(code) = exit {
+                    return Ok(code);
+                }
+            }
+
+            Ok::<_, eyre::Report>(0)
+        };
+
+        (handle, handle_events)
+    }
+}
 
-        let state = Fragment::spawn(&mut self.world.lock().unwrap(), handle.clone(), None);
-        root.mount(state).await
+impl App {
+    /// Returns a handle to the app, usable before the app is run
+    pub fn handle(&self) -> AppRef {
+        AppRef {
+            world: self.world.clone(),
+            senders: Arc::new(Senders {
+                tx: self.tx.clone(),
+                priority_tx: self.priority_tx.clone(),
+                roots: self.roots.0.clone(),
+            }),
+            lifecycle: self.lifecycle.clone(),
+            pause: self.pause.clone(),
+            max_depth: self.max_depth,
+        }
     }
 }
 
@@ -75,12 +440,171 @@ impl Default for App {
 
 impl AppRef {
     /// Lock the world
-    pub fn world(&self) -> MutexGuard<World> {
+    pub fn world(&self) -> MutexGuard<'_, World> {
         self.world.lock().unwrap()
     }
 
+    /// Replaces the root widget of a running app, such as to switch between screens.
+    ///
+    /// The current root widget is dropped and the descendants of the root fragment are despawned,
+    /// after which `widget` is mounted in the same fragment. The app exits with code 0 once the
+    /// new root completes. Has no effect unless the app is driven by [`App::run`].
+    pub fn set_root(&self, widget: Box<dyn Widget<Output = ()> + Send>) {
+        self.senders.roots.send(widget).ok();
+    }
+
+    /// Sends an event to the event loop.
+    ///
+    /// [`Event::Exit`] and [`Event::ExitWith`] go through the priority queue, so that exiting
+    /// is not held up by a backlog of other events.
     pub fn enqueue(&self, event: Event) -> Result<(), flume::SendError<Event>> {
-        self.tx.send(event)
+        if matches!(event, Event::Exit | Event::ExitWith(_)) {
+            self.enqueue_priority(event)?;
+            // Exiting forces the pending events to be processed
+            self.resume();
+        } else {
+            self.senders.tx.send(event)?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends an event which the event loop handles before any events enqueued through
+    /// [`enqueue`](Self::enqueue), such as in response to user input.
+    pub fn enqueue_priority(&self, event: Event) -> Result<(), flume::SendError<Event>> {
+        self.senders.priority_tx.send(event)
+    }
+
+    /// Pauses the app. Enqueued events are held back and the root widget is not polled until
+    /// [`resume`](Self::resume) is called.
+    ///
+    /// Tasks spawned outside of the root keep running. Enqueueing [`Event::Exit`] or
+    /// [`Event::ExitWith`] resumes the app so that it can exit.
+    pub fn pause(&self) {
+        self.pause.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Resumes a paused app, applying the events which were enqueued in the meantime
+    pub fn resume(&self) {
+        self.pause.paused.store(false, Ordering::SeqCst);
+        for waker in self.pause.wakers.lock().unwrap().drain(..) {
+            waker.wake()
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.pause.paused.load(Ordering::SeqCst)
+    }
+
+    /// Enqueue several events in sequence.
+    ///
+    /// The event loop drains all pending events each time it locks the world, so events
+    /// enqueued together are applied in the same batch without other tasks observing the world
+    /// in between.
+    pub fn enqueue_all(
+        &self,
+        events: impl IntoIterator<Item = Event>,
+    ) -> Result<(), flume::SendError<Event>> {
+        events.into_iter().try_for_each(|event| self.enqueue(event))
+    }
+
+    /// Spawns a future which is not `Send`, such as one holding graphics handles.
+    ///
+    /// The task runs on the [`LocalSet`] of [`App::run`], and is aborted when the app exits.
+    ///
+    /// # Panics
+    /// If not called from the task driving the root widget, e.g; from a `tokio::spawn`ed child.
+    pub fn spawn_local<F>(&self, fut: F) -> JoinHandle<F::Output>
+    where
+        F: 'static + Future,
+        F::Output: 'static,
+    {
+        tokio::task::spawn_local(fut)
+    }
+
+    /// Registers a callback which is invoked once the event loop despawns `id`, either directly or
+    /// as the descendant of a despawned entity, or once `id` is removed by
+    /// [`FragmentRef::clear`](crate::FragmentRef::clear).
+    ///
+    /// Each callback runs exactly once, after the world has been unlocked.
+    pub fn on_despawn(
+        &self,
+        id: Entity,
+        f: impl FnOnce(Entity) + Send + 'static,
+    ) -> Result<(), flax::Error> {
+        self.world()
+            .entry(id, despawn_callbacks())?
+            .or_default()
+            .get_mut()
+            .unwrap()
+            .push(Box::new(f));
+
+        Ok(())
+    }
+
+    /// Returns a stream of every entity and component which is inserted or modified among the
+    /// `components` watched, starting from now.
+    ///
+    /// Changes made between two polls are yielded together, once per entity and component.
+    pub fn change_stream(&self, components: impl IntoIterator<Item = Watched>) -> ChangeStream {
+        let mut watched = components.into_iter().collect::<Vec<_>>();
+        let keys = watched.iter().map(|w| w.key).collect::<Vec<_>>();
+        let (tx, rx) = notify::channel();
+
+        let mut world = self.world();
+        world.subscribe(ChangeSubscriber::new(&keys, tx));
+
+        // Skip the changes made before the stream was created
+        let mut skipped = VecDeque::new();
+        for w in &mut watched {
+            (w.changed)(&world, &mut skipped);
+        }
+
+        ChangeStream {
+            app: self.clone(),
+            rx,
+            watched,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Subscribes to changes of `components` on the entities matching `filter`, for as long as
+    /// the returned guard is held.
+    ///
+    /// Holding the guard in a widget's `mount` ends the subscription along with the widget,
+    /// instead of whenever the receiver happens to be dropped.
+    pub fn scoped_subscription<F>(
+        &self,
+        components: &[ComponentKey],
+        filter: F,
+    ) -> (NotifyReceiver, SubscriptionGuard)
+    where
+        F: StaticFilter + ComponentValue,
+    {
+        let (tx, rx) = notify::channel();
+        let (tx, guard) = notify::scoped(tx);
+        self.world()
+            .subscribe(ChangeSubscriber::new(components, tx).filter(filter));
+
+        (rx, guard)
+    }
+
+    /// Returns the fragments which are still mounted although no running widget or parent owns
+    /// them, such as those whose widget future was leaked rather than dropped.
+    ///
+    /// These are logged when [`App::run`] exits. Always empty in release builds.
+    pub fn leaked_fragments(&self) -> Vec<Entity> {
+        self.lifecycle.leaked(&self.world())
+    }
+
+    pub(crate) fn downgrade(&self) -> WeakAppRef {
+        WeakAppRef {
+            world: Arc::downgrade(&self.world),
+            senders: Arc::downgrade(&self.senders),
+            lifecycle: self.lifecycle.clone(),
+            pause: self.pause.clone(),
+            max_depth: self.max_depth,
+        }
     }
 }
 
@@ -88,11 +612,1063 @@ impl AppRef {
 #[derive(Debug, Clone)]
 pub struct AppRef {
     world: Arc<Mutex<World>>,
+    senders: Arc<Senders>,
+    pub(crate) lifecycle: Arc<Lifecycle>,
+    pause: Arc<Pause>,
+    pub(crate) max_depth: Option<usize>,
+}
+
+/// The sending halves of the app's channels.
+///
+/// Shared by the clones of an [`AppRef`], such that the event loop learns when the last handle
+/// is dropped.
+#[derive(Debug)]
+struct Senders {
     tx: Sender<Event>,
+    priority_tx: Sender<Event>,
+    roots: Sender<BoxedRoot>,
+}
+
+#[derive(Debug, Default)]
+struct Pause {
+    paused: AtomicBool,
+    wakers: Mutex<Vec<Waker>>,
 }
 
+impl Pause {
+    fn poll_resumed(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if !self.paused.load(Ordering::SeqCst) {
+            return Poll::Ready(());
+        }
+
+        // A task polled again while still paused is already registered
+        let mut wakers = self.wakers.lock().unwrap();
+        if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        }
+        drop(wakers);
+
+        // Resumed while registering
+        if self.paused.load(Ordering::SeqCst) {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+
+    /// Wraps `fut` so that it is not polled while paused
+    fn gate<F: Future>(self: Arc<Self>, fut: F) -> impl Future<Output = F::Output> {
+        let mut fut = Box::pin(fut);
+        poll_fn(move |cx| {
+            futures::ready!(self.poll_resumed(cx));
+            fut.as_mut().poll(cx)
+        })
+    }
+}
+
+type BoxedRoot = Box<dyn Widget<Output = ()> + Send>;
+
+pub(crate) type DespawnCallback = Box<dyn FnOnce(Entity) + Send>;
+
+component! {
+    pub(crate) despawn_callbacks: Mutex<Vec<DespawnCallback>>,
+}
+
+/// Removes the despawn callbacks of `id` and all its descendants
+pub(crate) fn take_despawn_callbacks(
+    world: &mut World,
+    id: Entity,
+    out: &mut Vec<(Entity, DespawnCallback)>,
+) {
+    for child in children_of(world, id) {
+        take_despawn_callbacks(world, child, out);
+    }
+
+    if let Ok(callbacks) = world.remove(id, despawn_callbacks()) {
+        out.extend(callbacks.into_inner().unwrap().into_iter().map(|f| (id, f)));
+    }
+}
+
+/// An [`AppRef`] which does not keep the world or event loop alive
 #[derive(Debug, Clone)]
+pub(crate) struct WeakAppRef {
+    world: Weak<Mutex<World>>,
+    senders: Weak<Senders>,
+    lifecycle: Arc<Lifecycle>,
+    pause: Arc<Pause>,
+    max_depth: Option<usize>,
+}
+
+impl WeakAppRef {
+    pub(crate) fn upgrade(&self) -> Option<AppRef> {
+        Some(AppRef {
+            world: self.world.upgrade()?,
+            senders: self.senders.upgrade()?,
+            lifecycle: self.lifecycle.clone(),
+            pause: self.pause.clone(),
+            max_depth: self.max_depth,
+        })
+    }
+}
+
+type ChangedFn = Box<dyn FnMut(&World, &mut VecDeque<(Entity, ComponentKey)>) + Send>;
+
+/// A component watched by [`AppRef::change_stream`]
+pub struct Watched {
+    key: ComponentKey,
+    changed: ChangedFn,
+}
+
+impl<T: ComponentValue> From<Component<T>> for Watched {
+    fn from(component: Component<T>) -> Self {
+        let key = component.key();
+        let mut query =
+            Query::new(entity_ids()).filter(component.modified() | component.inserted());
+
+        Self {
+            key,
+            changed: Box::new(move |world, out| {
+                out.extend(query.borrow(world).iter().map(|id| (id, key)))
+            }),
+        }
+    }
+}
+
+/// See [`AppRef::change_stream`]
+pub struct ChangeStream {
+    app: AppRef,
+    rx: NotifyReceiver,
+    watched: Vec<Watched>,
+    pending: VecDeque<(Entity, ComponentKey)>,
+}
+
+impl Stream for ChangeStream {
+    type Item = (Entity, ComponentKey);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Poll::Ready(Some(item));
+            }
+
+            futures::ready!(self.rx.poll_unpin(cx));
+
+            let this = &mut *self;
+            let world = this.app.world();
+            for w in &mut this.watched {
+                (w.changed)(&world, &mut this.pending);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
 pub enum Event {
+    /// Despawn the entity and all its descendants
     Despawn(Entity),
+    /// Set the components in the buffer on the entity
+    SetComponents(Entity, ComponentBuffer),
+    /// Stop the app with exit code 0
     Exit,
+    /// Stop the app with the given exit code
+    ExitWith(i32),
+    /// An event defined by the application, along with the name of its type. See
+    /// [`App::on_user_event`]
+    User(&'static str, Box<dyn Any + Send>),
+}
+
+/// Output of a root widget which can be returned from [`App::run`]
+pub trait IntoExitCode {
+    fn into_exit_code(self) -> eyre::Result<i32>;
+}
+
+impl IntoExitCode for () {
+    fn into_exit_code(self) -> eyre::Result<i32> {
+        Ok(0)
+    }
+}
+
+impl IntoExitCode for i32 {
+    fn into_exit_code(self) -> eyre::Result<i32> {
+        Ok(self)
+    }
+}
+
+impl<T, E> IntoExitCode for Result<T, E>
+where
+    T: IntoExitCode,
+    E: Into<eyre::Report>,
+{
+    fn into_exit_code(self) -> eyre::Result<i32> {
+        self.map_err(Into::into)?.into_exit_code()
+    }
+}
+
+impl Event {
+    /// Wraps an event defined by the application, which is passed to the handlers registered for
+    /// its type through [`App::on_user_event`]
+    pub fn user<E: Any + Send>(event: E) -> Self {
+        Self::User(type_name::<E>(), Box::new(event))
+    }
+
+    /// Set a single component value on the entity
+    pub fn set_component<T: ComponentValue>(id: Entity, component: Component<T>, value: T) -> Self {
+        let mut buffer = ComponentBuffer::new();
+        buffer.set(component, value);
+        Self::SetComponents(id, buffer)
+    }
+}
+
+/// The difference between two states of the renderable entities in a world
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WorldDiff {
+    /// Entities which only exist in the new world
+    pub added: Vec<Entity>,
+    /// Entities which only exist in the old world
+    pub removed: Vec<Entity>,
+    /// Components which were added, removed or modified on entities existing in both worlds
+    pub changed: Vec<(Entity, ComponentKey)>,
+}
+
+impl WorldDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Computes the difference of the [`widget`] entities between `old` and `new`.
+///
+/// Entities are matched by id, and only the components which affect rendering are compared.
+pub fn diff_worlds(old: &World, new: &World) -> WorldDiff {
+    let widgets = |world| -> BTreeSet<Entity> {
+        Query::new(entity_ids())
+            .with(widget())
+            .borrow(world)
+            .iter()
+            .collect()
+    };
+
+    let old_ids = widgets(old);
+    let new_ids = widgets(new);
+
+    let mut changed = Vec::new();
+    for &id in old_ids.intersection(&new_ids) {
+        let mut diff = |key, is_changed: bool| {
+            if is_changed {
+                changed.push((id, key))
+            }
+        };
+
+        diff(
+            position().key(),
+            component_changed(old, new, id, position()),
+        );
+        diff(size().key(), component_changed(old, new, id, size()));
+        diff(content().key(), component_changed(old, new, id, content()));
+        diff(canvas().key(), component_changed(old, new, id, canvas()));
+        diff(order().key(), component_changed(old, new, id, order()));
+        diff(role().key(), component_changed(old, new, id, role()));
+        diff(
+            aria_label().key(),
+            component_changed(old, new, id, aria_label()),
+        );
+
+        for tag in [disabled(), selected(), checked()] {
+            diff(tag.key(), old.has(id, tag) != new.has(id, tag));
+        }
+    }
+
+    WorldDiff {
+        added: new_ids.difference(&old_ids).copied().collect(),
+        removed: old_ids.difference(&new_ids).copied().collect(),
+        changed,
+    }
+}
+
+/// A component of a fragment which is saved by [`save_state`], such as a scroll offset.
+///
+/// The value is stored under the component's name.
+#[derive(Clone)]
+pub struct Persisted {
+    name: &'static str,
+    save: Arc<SaveFn>,
+    load: Arc<LoadFn>,
+}
+
+type SaveFn = dyn Fn(&World, Entity) -> Option<serde_json::Value> + Send + Sync;
+type LoadFn = dyn Fn(&mut World, Entity, serde_json::Value) -> serde_json::Result<()> + Send + Sync;
+
+impl Persisted {
+    pub fn new<T>(component: Component<T>) -> Self
+    where
+        T: ComponentValue + Serialize + DeserializeOwned,
+    {
+        Self {
+            name: component.name(),
+            save: Arc::new(move |world, id| {
+                let value = world.get(id, component).ok()?;
+                serde_json::to_value(&*value).ok()
+            }),
+            load: Arc::new(move |world, id, value| {
+                let value = serde_json::from_value(value)?;
+                world.set(id, component, value).unwrap();
+                Ok(())
+            }),
+        }
+    }
+}
+
+impl std::fmt::Debug for Persisted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Persisted").field(&self.name).finish()
+    }
+}
+
+/// Calls `f` with `id` and each of its descendants which have [`persistent`] components, along
+/// with their [name path](Fragment::name_path).
+fn visit_persistent(world: &World, id: Entity, f: &mut impl FnMut(Entity, &str)) {
+    if world.has(id, persistent()) {
+        f(id, &name_path(world, id));
+    }
+
+    for child in children_of(world, id) {
+        visit_persistent(world, child, f);
+    }
+}
+
+/// Saves the [`persistent`] components of `root` and its descendants, to be restored across runs
+/// by [`load_state`].
+///
+/// Fragments are identified by their [name path](Fragment::name_path). Naming the fragments
+/// which hold state keeps it restorable when the widgets around them change.
+pub fn save_state(world: &World, root: Entity) -> serde_json::Value {
+    let mut state = serde_json::Map::new();
+    visit_persistent(world, root, &mut |id, path| {
+        let values = world
+            .get(id, persistent())
+            .unwrap()
+            .iter()
+            .filter_map(|persisted| Some((persisted.name.into(), (persisted.save)(world, id)?)))
+            .collect::<serde_json::Map<_, _>>();
+
+        state.insert(path.into(), values.into());
+    });
+
+    state.into()
+}
+
+/// Restores the [`persistent`] components saved by [`save_state`].
+///
+/// Values of fragments or components which no longer exist are ignored.
+pub fn load_state(
+    world: &mut World,
+    root: Entity,
+    state: &serde_json::Value,
+) -> serde_json::Result<()> {
+    let mut targets = Vec::new();
+    visit_persistent(world, root, &mut |id, path| {
+        targets.push((id, path.to_string()))
+    });
+
+    for (id, path) in targets {
+        let Some(values) = state.get(&path) else {
+            continue;
+        };
+
+        let persisted = world.get(id, persistent()).unwrap().clone();
+        for persisted in persisted {
+            if let Some(value) = values.get(persisted.name) {
+                (persisted.load)(world, id, value.clone())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn component_changed<T: ComponentValue + PartialEq>(
+    old: &World,
+    new: &World,
+    id: Entity,
+    component: Component<T>,
+) -> bool {
+    match (old.get(id, component), new.get(id, component)) {
+        (Ok(old), Ok(new)) => *old != *new,
+        (Err(_), Err(_)) => false,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use futures::{FutureExt, StreamExt};
+    use parking_lot::Mutex;
+
+    use glam::vec2;
+
+    use crate::{terminal::on_char, widgets::Text};
+
+    use super::*;
+
+    struct Nested {
+        depth: usize,
+        mounted: Arc<Mutex<Vec<Entity>>>,
+    }
+
+    #[async_trait]
+    impl Widget for Nested {
+        type Output = ();
+
+        async fn mount(self, mut fragment: Fragment) {
+            self.mounted.lock().push(fragment.id());
+
+            if self.depth > 0 {
+                fragment
+                    .attach(Nested {
+                        depth: self.depth - 1,
+                        mounted: self.mounted,
+                    })
+                    .await
+            } else {
+                futures::future::pending().await
+            }
+        }
+    }
+
+    #[test]
+    fn nested_despawn_deepest_first() {
+        let app = App::new();
+        let mut root = Fragment::spawn(&mut app.world.lock().unwrap(), app.handle(), None);
+
+        let mounted = Arc::new(Mutex::new(Vec::new()));
+        let mut fut = root.attach(Nested {
+            depth: 2,
+            mounted: mounted.clone(),
+        });
+
+        assert!((&mut fut).now_or_never().is_none());
+        drop(fut);
+
+        let mounted = mounted.lock().clone();
+        assert_eq!(mounted.len(), 3);
+
+        let despawned = app
+            .rx
+            .drain()
+            .map(|event| match event {
+                Event::Despawn(id) => id,
+                event => panic!("Unexpected event: {event:?}"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(despawned, mounted.into_iter().rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn despawned_subtree_in_one_batch() {
+        let (handle, events) = App::new().start();
+        let mut events = Box::pin(events);
+
+        let mut world = handle.world();
+        let root = Fragment::spawn(&mut world, handle.clone(), None).id();
+        let mid = Fragment::spawn(&mut world, handle.clone(), Some(root)).id();
+        let leaf = Fragment::spawn(&mut world, handle.clone(), Some(mid)).id();
+        let other = world.spawn();
+        drop(world);
+
+        // Parent first, as when a cleared fragment is followed by the drops of its children
+        handle
+            .enqueue_all([
+                Event::Despawn(root),
+                Event::Despawn(mid),
+                Event::Despawn(leaf),
+                Event::Despawn(root),
+                Event::set_component(other, content(), "Still running".into()),
+            ])
+            .unwrap();
+
+        assert!(events.as_mut().now_or_never().is_none());
+
+        let world = handle.world();
+        assert!([root, mid, leaf].iter().all(|&id| !world.is_alive(id)));
+        assert_eq!(
+            world.get(other, content()).as_deref(),
+            Ok(&"Still running".into())
+        );
+    }
+
+    #[test]
+    fn enqueue_all_applies_in_one_batch() {
+        let (handle, events) = App::new().start();
+        let mut events = Box::pin(events);
+
+        let mut world = handle.world();
+        let a = world.spawn();
+        let b = world.spawn();
+        drop(world);
+
+        handle
+            .enqueue_all([
+                Event::set_component(a, content(), "Hello".into()),
+                Event::Despawn(b),
+            ])
+            .unwrap();
+
+        assert!(events.as_mut().now_or_never().is_none());
+
+        let world = handle.world();
+        assert_eq!(*world.get(a, content()).unwrap(), "Hello");
+        assert!(!world.is_alive(b));
+    }
+
+    #[test]
+    fn diff_changed_component() {
+        let mut old = World::new();
+
+        let a = Entity::builder()
+            .tag(widget())
+            .set(position(), vec2(1.0, 2.0))
+            .set(content(), "Hello".into())
+            .spawn(&mut old);
+
+        let removed = Entity::builder().tag(widget()).spawn(&mut old);
+
+        let mut new = World::new();
+        new.spawn_at(a).unwrap();
+        new.set(a, widget(), ()).unwrap();
+        new.set(a, position(), vec2(1.0, 2.0)).unwrap();
+        new.set(a, content(), "World".into()).unwrap();
+
+        // No longer a widget
+        new.spawn_at(removed).unwrap();
+
+        let added = Entity::builder().tag(widget()).spawn(&mut new);
+
+        assert_eq!(
+            diff_worlds(&old, &new),
+            WorldDiff {
+                added: vec![added],
+                removed: vec![removed],
+                changed: vec![(a, content().key())],
+            }
+        );
+
+        assert!(diff_worlds(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn frame_stage_spans() {
+        use tracing_subscriber::{filter::LevelFilter, layer::Context, prelude::*, Layer};
+
+        use crate::{
+            canvas::{draw_widgets, Canvas},
+            components::constraints,
+            layout::Constraints,
+            widgets::Column,
+        };
+
+        #[derive(Default, Clone)]
+        struct Spans(Arc<Mutex<Vec<&'static str>>>);
+
+        impl<S: tracing::Subscriber> Layer<S> for Spans {
+            fn on_new_span(
+                &self,
+                attrs: &tracing::span::Attributes<'_>,
+                _: &tracing::span::Id,
+                _: Context<'_, S>,
+            ) {
+                self.0.lock().push(attrs.metadata().name())
+            }
+        }
+
+        let (handle, events) = App::new().start();
+        let mut events = Box::pin(events);
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let mut column = Box::pin(root.attach(Column::new((Text::new("ab"), Text::new("c")))));
+        let column_id = column.id();
+        // Mounted before capturing, so that only the stages of each frame are recorded
+        assert!(column.as_mut().now_or_never().is_none());
+
+        let spans = Spans::default();
+        let _guard = tracing_subscriber::registry()
+            .with(spans.clone().with_filter(LevelFilter::DEBUG))
+            .set_default();
+
+        let mut target = Canvas::new(glam::uvec2(8, 2));
+        for width in [10.0, 20.0] {
+            handle
+                .enqueue(Event::set_component(
+                    column_id,
+                    constraints(),
+                    Constraints::new(Vec2::ZERO, vec2(width, f32::INFINITY)),
+                ))
+                .unwrap();
+            assert!(events.as_mut().now_or_never().is_none());
+            assert!(column.as_mut().now_or_never().is_none());
+            draw_widgets(&handle.world(), &mut target);
+        }
+
+        assert_eq!(
+            *spans.0.lock(),
+            ["events", "layout", "render", "events", "layout", "render"]
+        );
+    }
+
+    #[test]
+    fn on_despawn_callback() {
+        let (handle, events) = App::new().start();
+        let mut events = Box::pin(events);
+
+        let mut world = handle.world();
+        let parent = world.spawn();
+        let child = Entity::builder().tag(child_of(parent)).spawn(&mut world);
+        drop(world);
+
+        let despawned = Arc::new(Mutex::new(Vec::new()));
+        for id in [parent, child] {
+            let despawned = despawned.clone();
+            handle
+                .on_despawn(id, move |id| despawned.lock().push(id))
+                .unwrap();
+        }
+
+        handle.enqueue(Event::Despawn(parent)).unwrap();
+        assert!(events.as_mut().now_or_never().is_none());
+
+        assert_eq!(*despawned.lock(), [child, parent]);
+    }
+
+    #[test]
+    fn paused_holds_events() {
+        let (handle, events) = App::new().start();
+        let mut events = Box::pin(events);
+        let id = handle.world().spawn();
+
+        handle.pause();
+        handle
+            .enqueue(Event::set_component(id, content(), "Paused".into()))
+            .unwrap();
+        assert!(events.as_mut().now_or_never().is_none());
+        assert!(!handle.world().has(id, content()));
+
+        handle.resume();
+        assert!(events.as_mut().now_or_never().is_none());
+        assert_eq!(*handle.world().get(id, content()).unwrap(), "Paused");
+
+        // Exiting is not held back
+        handle.pause();
+        handle.enqueue(Event::ExitWith(2)).unwrap();
+        assert_eq!(events.as_mut().now_or_never().unwrap().unwrap(), 2);
+    }
+
+    #[test]
+    fn repolling_while_paused_registers_once() {
+        let pause = Arc::new(Pause::default());
+        pause.paused.store(true, Ordering::SeqCst);
+
+        let mut gated = Box::pin(pause.clone().gate(async {}));
+        for _ in 0..100 {
+            assert!(gated.as_mut().now_or_never().is_none());
+        }
+        assert_eq!(pause.wakers.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn exit_skips_normal_backlog() {
+        let (handle, events) = App::new().start();
+        let mut events = Box::pin(events);
+        let id = handle.world().spawn();
+
+        handle
+            .enqueue_all((0..1000).map(|i| Event::set_component(id, content(), i.to_string())))
+            .unwrap();
+        handle.enqueue(Event::ExitWith(3)).unwrap();
+
+        assert_eq!(events.as_mut().now_or_never().unwrap().unwrap(), 3);
+        assert!(!handle.world().has(id, content()));
+    }
+
+    #[test]
+    fn dropped_handles_end_event_loop() {
+        let (handle, events) = App::new().start();
+        let mut events = Box::pin(events);
+
+        let mut fragment = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let weak = fragment.downgrade();
+        fragment
+            .write()
+            .on_event(on_char(), move |_, _: &World, _: &char| {
+                weak.upgrade_set(content(), String::new());
+            });
+
+        handle
+            .enqueue(Event::set_component(
+                fragment.id(),
+                content(),
+                "Last".into(),
+            ))
+            .unwrap();
+
+        let world = handle.world.clone();
+        drop((fragment, handle));
+
+        assert_eq!(events.as_mut().now_or_never().unwrap().unwrap(), 0);
+        let world = world.lock().unwrap();
+        let text = Query::new(content())
+            .borrow(&world)
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(text, ["Last"]);
+    }
+
+    #[test]
+    fn change_stream_yields_content() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        root.write().set(content(), "Before".into());
+
+        let mut changes = handle.change_stream([content().into()]);
+        assert!(changes.next().now_or_never().is_none());
+
+        let child = root.attach(Text::new("Hello"));
+        let id = child.id();
+        child.now_or_never().unwrap();
+
+        assert_eq!(
+            changes.next().now_or_never(),
+            Some(Some((id, content().key())))
+        );
+        assert!(changes.next().now_or_never().is_none());
+
+        root.write().set(content(), "After".into());
+        assert_eq!(
+            changes.next().now_or_never(),
+            Some(Some((root.id(), content().key())))
+        );
+    }
+
+    #[tokio::test]
+    async fn exit_with_code() {
+        struct Quit;
+
+        #[async_trait]
+        impl Widget for Quit {
+            type Output = ();
+
+            async fn mount(self, fragment: Fragment) {
+                fragment.app().enqueue(Event::ExitWith(3)).unwrap();
+                futures::future::pending().await
+            }
+        }
+
+        assert_eq!(App::new().run(Quit).await.unwrap(), 3);
+    }
+
+    #[test]
+    fn user_events_reach_handlers() {
+        #[derive(Debug)]
+        enum Command {
+            Rename(Entity, &'static str),
+        }
+
+        let mut app = App::new();
+        app.on_user_event(|world, command: &Command| match *command {
+            Command::Rename(id, name) => {
+                world.set(id, content(), name.into()).unwrap();
+            }
+        });
+        // Events of other types are ignored
+        app.on_user_event(|_, _: &u32| unreachable!());
+
+        let (handle, events) = app.start();
+        let mut events = Box::pin(events);
+        let root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        handle
+            .enqueue(Event::user(Command::Rename(root.id(), "Settings")))
+            .unwrap();
+        assert!(events.as_mut().now_or_never().is_none());
+
+        assert_eq!(
+            handle.world().get(root.id(), content()).as_deref(),
+            Ok(&"Settings".into())
+        );
+
+        // Unhandled events are dropped without stopping the loop
+        handle.enqueue(Event::user("Unhandled")).unwrap();
+        assert!(events.as_mut().now_or_never().is_none());
+    }
+
+    #[tokio::test]
+    async fn root_error_is_returned() {
+        struct Failing;
+
+        #[async_trait]
+        impl Widget for Failing {
+            type Output = eyre::Result<()>;
+
+            async fn mount(self, _: Fragment) -> eyre::Result<()> {
+                Err(eyre::eyre!("Failed to load"))
+            }
+        }
+
+        let finalized = Arc::new(Mutex::new(false));
+        let mut app = App::new();
+        {
+            let finalized = finalized.clone();
+            app.on_exit(move |_| {
+                async move {
+                    *finalized.lock() = true;
+                }
+                .boxed()
+            });
+        }
+
+        let err = app.run(Failing).await.unwrap_err();
+        assert_eq!(err.to_string(), "Failed to load");
+        assert!(*finalized.lock());
+    }
+
+    #[tokio::test]
+    async fn finalizers_run_before_returning() {
+        struct Quit;
+
+        #[async_trait]
+        impl Widget for Quit {
+            type Output = ();
+
+            async fn mount(self, fragment: Fragment) {
+                fragment.app().enqueue(Event::Exit).unwrap();
+                futures::future::pending().await
+            }
+        }
+
+        let finalized = Arc::new(Mutex::new(Vec::new()));
+        let mut app = App::new();
+        for i in 0..2 {
+            let finalized = finalized.clone();
+            app.on_exit(move |_| {
+                async move {
+                    tokio::task::yield_now().await;
+                    finalized.lock().push(i);
+                }
+                .boxed()
+            });
+        }
+
+        app.run(Quit).await.unwrap();
+        assert_eq!(*finalized.lock(), [0, 1]);
+    }
+
+    #[test]
+    fn scoped_subscription_ends_with_scope() {
+        let app = App::new();
+        let handle = app.handle();
+        let id = handle.world().spawn();
+
+        let (mut changed, guard) = handle.scoped_subscription(&[content().key()], flax::All);
+        let mut scope = Box::pin(async move {
+            let _guard = guard;
+            tokio::task::yield_now().await;
+        });
+
+        handle.world().set(id, content(), "a".into()).unwrap();
+        assert_eq!((&mut changed).now_or_never(), Some(()));
+
+        assert_eq!(scope.as_mut().now_or_never(), None);
+        assert_eq!(scope.now_or_never(), Some(()));
+
+        handle.world().set(id, content(), "b".into()).unwrap();
+        assert_eq!((&mut changed).now_or_never(), None);
+    }
+
+    #[test]
+    fn persisted_scroll_offset_restored() {
+        component! {
+            scroll_offset: f32,
+        }
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut world = handle.world();
+        let root = Fragment::spawn(&mut world, handle.clone(), None);
+        let mut list = Fragment::spawn(&mut world, handle.clone(), Some(root.id()));
+        drop(world);
+
+        list.write()
+            .set(scroll_offset(), 12.0)
+            .persist(scroll_offset());
+
+        let state = save_state(&handle.world(), root.id());
+        assert_eq!(state, serde_json::json!({ "0": { "scroll_offset": 12.0 } }));
+
+        list.write().set(scroll_offset(), 0.0);
+        load_state(&mut handle.world(), root.id(), &state).unwrap();
+        assert_eq!(
+            *handle.world().get(list.id(), scroll_offset()).unwrap(),
+            12.0
+        );
+    }
+
+    #[tokio::test]
+    async fn set_root_swaps_screens() {
+        struct Screen {
+            name: &'static str,
+            next: Option<Box<Screen>>,
+            mounted: Arc<Mutex<Vec<(Entity, Entity)>>>,
+        }
+
+        #[async_trait]
+        impl Widget for Screen {
+            type Output = ();
+
+            async fn mount(self, mut fragment: Fragment) {
+                let text = fragment.attach(Text::new(self.name));
+                self.mounted.lock().push((fragment.id(), text.id()));
+                text.await;
+
+                match self.next {
+                    Some(next) => fragment.app().set_root(next),
+                    None => fragment.app().enqueue(Event::Exit).unwrap(),
+                }
+
+                futures::future::pending().await
+            }
+        }
+
+        let mounted = Arc::new(Mutex::new(Vec::new()));
+        let dashboard = Screen {
+            name: "dashboard",
+            next: None,
+            mounted: mounted.clone(),
+        };
+        let login = Screen {
+            name: "login",
+            next: Some(Box::new(dashboard)),
+            mounted: mounted.clone(),
+        };
+
+        let app = App::new();
+        let handle = app.handle();
+        app.run(login).await.unwrap();
+
+        let mounted = mounted.lock();
+        let [(login_root, login_text), (root, text)] = mounted[..] else {
+            panic!("Expected two screens to be mounted, got {mounted:?}");
+        };
+
+        let world = handle.world();
+        assert_eq!(login_root, root);
+        assert!(!world.is_alive(login_text));
+        assert_eq!(children_of(&world, root), [text]);
+        assert_eq!(*world.get(text, content()).unwrap(), "dashboard");
+    }
+
+    #[tokio::test]
+    async fn leaked_fragment_reported() {
+        struct Pending;
+
+        #[async_trait]
+        impl Widget for Pending {
+            type Output = ();
+
+            async fn mount(self, _: Fragment) {
+                futures::future::pending().await
+            }
+        }
+
+        struct Leaky(Arc<Mutex<Option<Entity>>>);
+
+        #[async_trait]
+        impl Widget for Leaky {
+            type Output = ();
+
+            async fn mount(self, mut fragment: Fragment) {
+                fragment.attach(Text::new("Completed")).await;
+                let running = fragment.attach(Pending);
+
+                let forgotten = fragment.attach(Pending);
+                *self.0.lock() = Some(forgotten.id());
+                std::mem::forget(forgotten);
+
+                fragment.app().enqueue(Event::Exit).unwrap();
+                running.await
+            }
+        }
+
+        let app = App::new();
+        let handle = app.handle();
+        let forgotten = Arc::new(Mutex::new(None));
+        app.run(Leaky(forgotten.clone())).await.unwrap();
+
+        assert_eq!(handle.leaked_fragments(), [forgotten.lock().unwrap()]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn ctrlc_exits() {
+        use std::sync::atomic::AtomicBool;
+
+        struct Terminal(Arc<AtomicBool>);
+
+        impl Drop for Terminal {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        struct Interrupted(Arc<AtomicBool>);
+
+        #[async_trait]
+        impl Widget for Interrupted {
+            type Output = ();
+
+            async fn mount(self, _: Fragment) {
+                let _terminal = Terminal(self.0);
+                // Let the signal handler be installed
+                tokio::task::yield_now().await;
+                unsafe { libc::raise(libc::SIGINT) };
+                futures::future::pending().await
+            }
+        }
+
+        let restored = Arc::new(AtomicBool::new(false));
+        let code = App::new()
+            .with_ctrlc_exit()
+            .run(Interrupted(restored.clone()))
+            .await
+            .unwrap();
+
+        assert_eq!(code, 0);
+        assert!(restored.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn spawn_local_task() {
+        struct Local;
+
+        #[async_trait]
+        impl Widget for Local {
+            type Output = i32;
+
+            async fn mount(self, fragment: Fragment) -> i32 {
+                fragment
+                    .app()
+                    .spawn_local(async {
+                        let value = std::rc::Rc::new(5);
+                        tokio::task::yield_now().await;
+                        *value
+                    })
+                    .await
+                    .unwrap()
+            }
+        }
+
+        assert_eq!(App::new().run(Local).await.unwrap(), 5);
+    }
 }
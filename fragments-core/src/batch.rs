@@ -0,0 +1,198 @@
+//! Coalescing of change notifications for writes made in a [`Fragment::batch`].
+use std::{cell::RefCell, sync::Arc};
+
+use flax::events::{ChangeEvent, EventHandler};
+
+#[cfg(doc)]
+use crate::Fragment;
+
+type Pending = Vec<(
+    Arc<dyn EventHandler<ChangeEvent> + Send + Sync>,
+    ChangeEvent,
+)>;
+
+thread_local! {
+    /// Handlers waiting for the current batch on this thread to finish
+    static PENDING: RefCell<Option<Pending>> = const { RefCell::new(None) };
+}
+
+/// A change handler which is invoked at most once per batch, with the first change of the batch.
+///
+/// Outside of a batch every change is forwarded as usual.
+pub struct Coalesced<H> {
+    handler: Arc<H>,
+}
+
+impl<H> Coalesced<H> {
+    pub fn new(handler: H) -> Self {
+        Self {
+            handler: Arc::new(handler),
+        }
+    }
+}
+
+impl<H> EventHandler<ChangeEvent> for Coalesced<H>
+where
+    H: 'static + EventHandler<ChangeEvent> + Send + Sync,
+{
+    fn on_event(&self, event: ChangeEvent) -> bool {
+        let deferred = PENDING.with(|pending| {
+            let mut pending = pending.borrow_mut();
+            let Some(pending) = pending.as_mut() else {
+                return false;
+            };
+
+            let ptr = Arc::as_ptr(&self.handler) as *const ();
+            if !pending
+                .iter()
+                .any(|(handler, _)| Arc::as_ptr(handler) as *const () == ptr)
+            {
+                pending.push((self.handler.clone(), event.clone()));
+            }
+
+            true
+        });
+
+        deferred || self.handler.on_event(event)
+    }
+}
+
+/// Runs `f`, deferring [`Coalesced`] handlers until it returns.
+///
+/// Nested batches are flushed by the outermost one.
+pub(crate) fn scope<R>(f: impl FnOnce() -> R) -> R {
+    let outermost = PENDING.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        if pending.is_none() {
+            *pending = Some(Vec::new());
+            true
+        } else {
+            false
+        }
+    });
+
+    let _flush = outermost.then_some(Flush);
+    f()
+}
+
+/// Ends the outermost batch when dropped, such that a panicking batch does not leave the thread
+/// deferring changes forever
+struct Flush;
+
+impl Drop for Flush {
+    fn drop(&mut self) {
+        let pending = PENDING.with(|pending| pending.borrow_mut().take().unwrap_or_default());
+        for (handler, event) in pending {
+            handler.on_event(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use flax::events::ChangeSubscriber;
+    use glam::Vec2;
+
+    use crate::{
+        app::App,
+        components::{content, position, size},
+        Fragment,
+    };
+
+    use super::*;
+
+    #[test]
+    fn batch_wakes_once() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let wakeups = Arc::new(AtomicUsize::new(0));
+        {
+            let wakeups = wakeups.clone();
+            handle.world().subscribe(ChangeSubscriber::new(
+                &[position().key(), size().key(), content().key()],
+                Coalesced::new(move |_| {
+                    wakeups.fetch_add(1, Ordering::Relaxed);
+                    true
+                }),
+            ));
+        }
+
+        root.batch(|frag| {
+            frag.set(position(), Vec2::ZERO)
+                .set(size(), Vec2::ONE)
+                .set(content(), "Hello".into());
+        });
+
+        assert_eq!(wakeups.load(Ordering::Relaxed), 1);
+
+        root.write()
+            .set(position(), Vec2::ONE)
+            .set(size(), Vec2::ZERO);
+
+        assert_eq!(wakeups.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn notify_woken_after_batch() {
+        use futures::FutureExt;
+        use tokio::sync::Notify;
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        // Subscribed like the layout widgets and renderers
+        let changed = Arc::new(Notify::new());
+        handle.world().subscribe(ChangeSubscriber::new(
+            &[position().key(), size().key(), content().key()],
+            Coalesced::new(Arc::downgrade(&changed)),
+        ));
+
+        root.batch(|frag| {
+            frag.set(position(), Vec2::ZERO)
+                .set(size(), Vec2::ONE)
+                .set(content(), "Hello".into());
+            assert!(changed.notified().now_or_never().is_none());
+        });
+
+        assert!(changed.notified().now_or_never().is_some());
+        assert!(changed.notified().now_or_never().is_none());
+    }
+
+    #[test]
+    fn panicking_batch_is_flushed() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let wakeups = Arc::new(AtomicUsize::new(0));
+        {
+            let wakeups = wakeups.clone();
+            handle.world().subscribe(ChangeSubscriber::new(
+                &[position().key()],
+                Coalesced::new(move |_| {
+                    wakeups.fetch_add(1, Ordering::Relaxed);
+                    true
+                }),
+            ));
+        }
+
+        // The world is unlocked before panicking, so that it is not poisoned
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            scope(|| {
+                root.write().set(position(), Vec2::ZERO);
+                panic!("Batch failed");
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(wakeups.load(Ordering::Relaxed), 1);
+
+        // No longer batching
+        root.write().set(position(), Vec2::ONE);
+        assert_eq!(wakeups.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,397 @@
+use std::fmt::{self, Display};
+
+use flax::{entity_ids, FetchExt, Query, World};
+use glam::{ivec2, IVec2, UVec2};
+use itertools::Itertools;
+use unicode_width::UnicodeWidthChar;
+
+use crate::{
+    components::{
+        background, canvas, content, layer, position, rich_content, size, style, validation,
+        widget, z_index,
+    },
+    fragment::is_hidden,
+    layout::absolute_position,
+    style::{Color, Style},
+};
+
+/// A single styled character
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub c: char,
+    pub style: Style,
+}
+
+/// A grid of character cells which widgets are composited onto before being presented.
+///
+/// Drawing outside of the canvas is clipped, and empty cells are transparent when composited onto
+/// another canvas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Canvas {
+    size: UVec2,
+    cells: Vec<Option<Cell>>,
+}
+
+impl Canvas {
+    /// Creates a new, fully transparent canvas
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            cells: vec![None; (size.x * size.y) as usize],
+        }
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    fn index(&self, pos: IVec2) -> Option<usize> {
+        if pos.x < 0 || pos.y < 0 || pos.x >= self.size.x as i32 || pos.y >= self.size.y as i32 {
+            None
+        } else {
+            Some((pos.y as u32 * self.size.x + pos.x as u32) as usize)
+        }
+    }
+
+    /// Returns the character at `pos`
+    pub fn get(&self, pos: IVec2) -> Option<char> {
+        self.cell(pos).map(|cell| cell.c)
+    }
+
+    /// Returns the styled cell at `pos`
+    pub fn cell(&self, pos: IVec2) -> Option<Cell> {
+        self.index(pos).and_then(|i| self.cells[i])
+    }
+
+    /// Sets the cell at `pos` using the default style
+    pub fn set(&mut self, pos: IVec2, c: char) {
+        self.set_styled(pos, c, Style::default())
+    }
+
+    /// Sets the cell at `pos`.
+    ///
+    /// Without a background color of its own, the cell keeps the background of the cell it
+    /// replaces.
+    pub fn set_styled(&mut self, pos: IVec2, c: char, mut style: Style) {
+        if let Some(i) = self.index(pos) {
+            if style.bg.is_none() {
+                style.bg = self.cells[i].and_then(|cell| cell.style.bg);
+            }
+
+            self.cells[i] = Some(Cell { c, style })
+        }
+    }
+
+    /// Fills the rectangle at `pos` spanning `size` with blank cells of the color
+    pub fn fill(&mut self, pos: IVec2, size: IVec2, color: Color) {
+        for y in pos.y..pos.y + size.y {
+            for x in pos.x..pos.x + size.x {
+                self.set_styled(ivec2(x, y), ' ', Style::new().bg(color));
+            }
+        }
+    }
+
+    /// Draws a single line of text starting at `pos`
+    pub fn draw_str(&mut self, pos: IVec2, text: &str) {
+        self.draw_str_styled(pos, text, Style::default());
+    }
+
+    /// Draws a single line of styled text starting at `pos`.
+    ///
+    /// Wide characters span several cells, of which the first holds the character and the rest
+    /// are cleared. Zero width characters are skipped.
+    ///
+    /// Returns the number of cells drawn, including those which were clipped.
+    pub fn draw_str_styled(&mut self, pos: IVec2, text: &str, style: Style) -> i32 {
+        let mut width = 0;
+        for c in text.chars() {
+            let w = c.width().unwrap_or(0) as i32;
+            if w == 0 {
+                continue;
+            }
+
+            self.set_styled(pos + ivec2(width, 0), c, style);
+            for x in 1..w {
+                if let Some(i) = self.index(pos + ivec2(width + x, 0)) {
+                    self.cells[i] = None;
+                }
+            }
+            width += w;
+        }
+
+        width
+    }
+
+    /// Draws a border along the edges of the rectangle at `pos` spanning `size`
+    pub fn draw_border(&mut self, pos: IVec2, size: IVec2) {
+        if size.x <= 0 || size.y <= 0 {
+            return;
+        }
+
+        let max = pos + size - 1;
+
+        for x in pos.x..=max.x {
+            self.set(ivec2(x, pos.y), '─');
+            self.set(ivec2(x, max.y), '─');
+        }
+
+        for y in pos.y..=max.y {
+            self.set(ivec2(pos.x, y), '│');
+            self.set(ivec2(max.x, y), '│');
+        }
+
+        self.set(pos, '┌');
+        self.set(ivec2(max.x, pos.y), '┐');
+        self.set(ivec2(pos.x, max.y), '└');
+        self.set(max, '┘');
+    }
+
+    /// Composites `other` onto this canvas at `pos`, skipping transparent cells
+    pub fn blit(&mut self, pos: IVec2, other: &Canvas) {
+        for y in 0..other.size.y as i32 {
+            for x in 0..other.size.x as i32 {
+                if let Some(cell) = other.cell(ivec2(x, y)) {
+                    self.set_styled(pos + ivec2(x, y), cell.c, cell.style)
+                }
+            }
+        }
+    }
+
+    /// Returns the rows of the canvas, with transparent cells as spaces.
+    ///
+    /// The cells covered by a wide character are left out, as the character fills them.
+    pub fn rows(&self) -> impl Iterator<Item = String> + '_ {
+        self.cells.chunks(self.size.x.max(1) as usize).map(|row| {
+            let mut covered = 0;
+            row.iter()
+                .filter_map(|cell| {
+                    if covered > 0 {
+                        covered -= 1;
+                        return None;
+                    }
+
+                    let c = cell.map_or(' ', |cell| cell.c);
+                    covered = cell_width(c) - 1;
+                    Some(c)
+                })
+                .collect()
+        })
+    }
+}
+
+impl Display for Canvas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, row) in self.rows().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{row}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the number of cells a character of a [`Cell`] spans
+pub(crate) fn cell_width(c: char) -> usize {
+    c.width().unwrap_or(1).max(1)
+}
+
+/// A group of widgets drawn together, in the order of declaration
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    Background,
+    #[default]
+    Content,
+    /// Drawn on top of everything else, e.g; popups and tooltips
+    Overlay,
+}
+
+/// Draws `text` at `cursor`, starting each new line at `left`, and wrapping lines which do not fit
+/// within `width` cells.
+///
+/// The cursor is left at the end of the text, so that drawing can continue from there.
+fn draw_lines(
+    target: &mut Canvas,
+    left: i32,
+    width: i32,
+    cursor: &mut IVec2,
+    text: &str,
+    style: Style,
+) {
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            *cursor = ivec2(left, cursor.y + 1);
+        }
+
+        for c in line.chars() {
+            // A row always fits at least one character
+            let w = c.width().unwrap_or(0) as i32;
+            if cursor.x > left && cursor.x - left + w > width {
+                *cursor = ivec2(left, cursor.y + 1);
+            }
+
+            cursor.x += target.draw_str_styled(*cursor, c.encode_utf8(&mut [0; 4]), style);
+        }
+    }
+}
+
+/// Draws the [`content`], [`rich_content`] and [`canvas`] of every widget in the world onto `target`.
+///
+/// Widgets are drawn at their [`absolute_position`]. Widgets without a [`position`] are not drawn,
+/// nor are [`hidden`](crate::components::hidden) subtrees. Text continues on the next row after
+/// each newline, and wraps at the width of the widget's [`size`], if any.
+///
+/// Each [`Layer`] is drawn in full before the next, and within a layer, widgets with a higher
+/// [`z_index`] are drawn later. [`background`]s are filled in before anything else of the layer,
+/// and widget canvases are composited after all text content. Failed [`validation`]s are drawn
+/// below their widget.
+pub fn draw_widgets(world: &World, target: &mut Canvas) {
+    let _span = tracing::debug_span!("render").entered();
+
+    let pos = |id| absolute_position(world, id).as_ivec2();
+    let width = |id| {
+        world
+            .get(id, size())
+            .ok()
+            .filter(|size| size.x >= 1.0)
+            .map_or(i32::MAX, |size| size.x as i32)
+    };
+
+    let mut widgets = Query::new((
+        entity_ids(),
+        layer().opt_or_default(),
+        z_index().opt_or_default(),
+    ))
+    .with(position())
+    .with(widget())
+    .borrow(world)
+    .iter()
+    .filter(|&(id, _, _)| !is_hidden(world, id))
+    .map(|(id, &layer, &z_index)| (layer, z_index, id))
+    .collect_vec();
+
+    widgets.sort_by_key(|&(layer, z_index, _)| (layer, z_index));
+
+    for (_, group) in &widgets.into_iter().group_by(|&(layer, _, _)| layer) {
+        let ids = group.map(|(_, _, id)| id).collect_vec();
+
+        for &id in &ids {
+            if let (Ok(color), Ok(size)) = (world.get(id, background()), world.get(id, size())) {
+                target.fill(pos(id), size.as_ivec2(), *color)
+            }
+        }
+
+        for &id in &ids {
+            if let Ok(content) = world.get(id, content()) {
+                let style = world
+                    .get(id, style())
+                    .map_or(Style::default(), |style| *style);
+                draw_lines(target, pos(id).x, width(id), &mut pos(id), &content, style);
+            }
+        }
+
+        for &id in &ids {
+            if let Ok(rich) = world.get(id, rich_content()) {
+                let mut cursor = pos(id);
+                for span in &rich.0 {
+                    let (left, width) = (pos(id).x, width(id));
+                    draw_lines(target, left, width, &mut cursor, &span.text, span.style);
+                }
+            }
+        }
+
+        for &id in &ids {
+            if let Ok(Err(error)) = world.get(id, validation()).as_deref() {
+                let height = world.get(id, size()).map_or(1.0, |size| size.y);
+                let below = pos(id) + ivec2(0, height as i32);
+                target.draw_str_styled(below, error, Style::new().fg(Color::Red));
+            }
+        }
+
+        for &id in &ids {
+            if let Ok(canvas) = world.get(id, canvas()) {
+                target.blit(pos(id), &canvas)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::uvec2;
+
+    use super::*;
+
+    #[test]
+    fn border_and_blit() {
+        let mut overlay = Canvas::new(uvec2(4, 3));
+        overlay.draw_border(IVec2::ZERO, ivec2(4, 3));
+
+        let mut canvas = Canvas::new(uvec2(6, 4));
+        canvas.draw_str(ivec2(2, 2), "abcdef");
+        canvas.blit(ivec2(1, 1), &overlay);
+
+        assert_eq!(canvas.to_string(), "      \n ┌──┐ \n │ab│d\n └──┘ ");
+    }
+
+    #[test]
+    fn wide_characters_span_cells() {
+        let mut canvas = Canvas::new(uvec2(6, 1));
+        canvas.draw_str(IVec2::ZERO, "xxxxxx");
+        let width = canvas.draw_str_styled(IVec2::ZERO, "漢e\u{301}字", Style::default());
+
+        assert_eq!(width, 5);
+        assert_eq!(canvas.get(ivec2(0, 0)), Some('漢'));
+        assert_eq!(canvas.get(ivec2(1, 0)), None);
+        assert_eq!(canvas.get(ivec2(2, 0)), Some('e'));
+        assert_eq!(canvas.get(ivec2(3, 0)), Some('字'));
+        assert_eq!(canvas.to_string(), "漢e字x");
+    }
+
+    #[test]
+    fn multiline_content() {
+        use glam::vec2;
+
+        use crate::{app::App, widgets::Text, Fragment};
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut text = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        text.put_sync(Text::new("ab\ncde\n"));
+        text.write().set(position(), vec2(1.0, 0.0));
+
+        let mut canvas = Canvas::new(uvec2(5, 3));
+        draw_widgets(&handle.world(), &mut canvas);
+        assert_eq!(canvas.to_string(), " ab  \n cde \n     ");
+    }
+
+    #[test]
+    fn overlay_layer_draws_on_top() {
+        use glam::vec2;
+
+        use crate::{app::App, Fragment};
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut world = handle.world();
+        let mut overlay = Fragment::spawn(&mut world, handle.clone(), None);
+        let mut body = Fragment::spawn(&mut world, handle.clone(), None);
+        drop(world);
+
+        overlay
+            .write()
+            .set(position(), vec2(0.0, 0.0))
+            .set(content(), "overlay".into())
+            .set(layer(), Layer::Overlay)
+            .set(z_index(), -5);
+        body.write()
+            .set(position(), vec2(0.0, 0.0))
+            .set(content(), "content".into())
+            .set(z_index(), 10);
+
+        let mut canvas = Canvas::new(uvec2(7, 1));
+        draw_widgets(&handle.world(), &mut canvas);
+        assert_eq!(canvas.to_string(), "overlay");
+    }
+}
@@ -1,8 +1,98 @@
+use std::sync::Arc;
+
 use flax::component;
 use glam::Vec2;
 
+use crate::{
+    accessibility::Role,
+    app::Persisted,
+    canvas::{Canvas, Layer},
+    geometry::Rect,
+    layout::{Constraints, Direction, Measure},
+    style::{Color, RichContent, Style},
+};
+
 component! {
     pub widget: (),
     pub size: Vec2,
     pub position:Vec2,
+    /// The size of the screen or window, set on the root fragment
+    pub viewport: Vec2,
+    /// Set on the root fragment when the app renders to an in-memory grid of the root's
+    /// [`viewport`] rather than to a terminal. See [`crate::app::App::with_viewport`]
+    pub headless: (),
+    /// Size as a fraction of the viewport. See [`crate::layout::RelativeLayout`]
+    pub relative_size: Vec2,
+    /// The direction content flows in, inherited by the subtree
+    pub direction: Direction,
+    /// Reports the size the widget wants given the available space. See [`crate::layout::measure`]
+    pub measure: Arc<dyn Measure>,
+    /// The sizes the widget's container allows it to take, set before the container lays it out.
+    /// Widgets which size themselves to the available space read them in `mount`. See
+    /// [`Constraints`]
+    pub constraints: Constraints,
+    /// Hash of the inputs to the last layout pass of a container, used to skip passes which
+    /// would not move anything. See [`crate::layout::hash_layout`]
+    pub layout_hash: u64,
+    /// Text drawn at the widget's position
+    pub content: String,
+    /// Styled text drawn at the widget's position
+    pub rich_content: RichContent,
+    /// Cells drawn at the widget's position, on top of all text content
+    pub canvas: Canvas,
+    /// The region which responds to the pointer, relative to the widget's position. Defaults to
+    /// the widget's [`size`]. See [`crate::geometry::hit_test`]
+    pub hit_rect: Rect,
+    /// Style of the widget's [`content`]
+    pub style: Style,
+    /// Names the widget is selected by in a [`Stylesheet`](crate::style::Stylesheet)
+    pub class: Vec<String>,
+    /// Region of the widget which changed since the last frame, relative to its position. Lets a
+    /// renderer redraw only part of the screen, and is removed once drawn.
+    ///
+    /// Set it before the changes it covers, as changes of widgets without one redraw everything.
+    /// See [`crate::terminal::take_dirty_region`]
+    pub dirty_rect: Rect,
+    /// Fills the widget's [`size`] with a color, underneath its content
+    pub background: Color,
+    /// The group of widgets the widget is drawn with. Defaults to [`Layer::Content`]
+    pub layer: Layer,
+    /// Widgets with a higher z-index are drawn on top of others in the same [`layer`]. Defaults
+    /// to 0.
+    pub z_index: i32,
+    /// The widget and its descendants are neither drawn nor hit by the pointer, but remain
+    /// mounted. Set on the inactive panels of [`Tabs`](crate::widgets::Tabs)
+    pub hidden: (),
+    /// The position of a fragment among its siblings. Layout widgets place children in ascending
+    /// order.
+    pub order: u32,
+
+    /// The widget does not respond to input. Event hooks are skipped.
+    pub disabled: (),
+    /// The widget is the current selection, e.g; an item in a list
+    pub selected: (),
+    /// The widget is checked, e.g; a checkbox or toggle
+    pub checked: (),
+    /// The widget can receive keyboard focus
+    pub focusable: (),
+    /// The widget has keyboard focus
+    pub focused: (),
+    /// Shows the terminal cursor while the widget has focus, such as for a text input
+    pub cursor_visible: bool,
+    /// Position of the terminal cursor relative to the widget's position
+    pub cursor_pos: Vec2,
+    /// Outcome of validating the widget's input. An error message is drawn below the widget
+    pub validation: Result<(), String>,
+    /// Overrides the position of a focusable widget in the Tab order. Widgets are focused in
+    /// ascending order, and those with a negative index are skipped. Defaults to 0.
+    pub tab_index: i32,
+
+    /// Components of the fragment which are saved and restored across runs. See
+    /// [`crate::app::save_state`]
+    pub persistent: Vec<Persisted>,
+
+    /// Accessible name of the widget, e.g; the text of a button
+    pub aria_label: String,
+    /// The semantic role of the widget
+    pub role: Role,
 }
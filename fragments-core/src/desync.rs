@@ -1,12 +1,12 @@
-use std::{
-    sync::{Arc, Condvar},
-    thread,
-};
+//! Ownership of a value by a background thread.
+use std::thread::{self, JoinHandle};
 
-use once_cell::sync::OnceCell;
-
-type Job<T> = Box<dyn Fn(&mut T)>;
+type Job<T> = Box<dyn FnOnce(&mut T) + Send>;
 
+/// A value which is owned by a dedicated thread, and accessed by sending it jobs.
+///
+/// This moves blocking work, such as file IO or heavy computation, off of the async executor.
+/// Jobs are sent through a [`DesyncRef`], and are applied in the order they were sent.
 pub struct Desync<T> {
     value: T,
     rx: flume::Receiver<Job<T>>,
@@ -37,7 +37,24 @@ impl<T> Desync<T> {
         }
     }
 
-    // pub async fn run(self) -> Self {}
+    /// Moves the value to a new thread, which applies the jobs as they arrive.
+    ///
+    /// Jobs sent before the thread was started are applied first. The thread stops once every
+    /// [`DesyncRef`] is dropped, and the value is returned by joining it.
+    pub fn run(self) -> JoinHandle<T>
+    where
+        T: 'static + Send,
+    {
+        let Self { mut value, rx, .. } = self;
+
+        thread::spawn(move || {
+            while let Ok(job) = rx.recv() {
+                job(&mut value);
+            }
+
+            value
+        })
+    }
 
     pub fn handle(&self) -> &DesyncRef<T> {
         &self.handle
@@ -46,33 +63,109 @@ impl<T> Desync<T> {
 
 impl<T> DesyncRef<T> {
     /// Perform an action on the contained value in the background
-    fn desync(&self, f: impl Fn(&mut T) + Send + 'static) {
+    pub fn desync(&self, f: impl FnOnce(&mut T) + Send + 'static) {
         self.tx.send(Box::new(f)).unwrap();
     }
 
-    /// Perform an action and return the result
-    fn sync<R: Send + 'static>(&self, f: impl Fn(&mut T) -> R + Send + 'static) -> R {
-        let tid = thread::current();
+    /// Perform an action and return the result.
+    ///
+    /// This blocks the current thread until the job has been applied, so it must not be called
+    /// from the thread running the value. This is for non-async callers, async code should use
+    /// [`Self::sync_async`] instead to not hold up the executor.
+    pub fn sync<R: Send + 'static>(&self, f: impl FnOnce(&mut T) -> R + Send + 'static) -> R {
+        let (tx, rx) = flume::bounded(1);
 
-        let result = Arc::new(OnceCell::new());
+        self.desync(move |v| {
+            tx.send(f(v)).ok();
+        });
 
-        let r = result.clone();
-        self.tx
-            .send(Box::new(move |v| {
-                if r.set(f(v)).is_err() {
-                    unreachable!()
-                }
-                tid.unpark();
-            }))
-            .unwrap();
+        rx.recv()
+            .expect("Desync thread stopped before applying the job")
+    }
 
-        thread::park();
+    /// Perform an action and wait for the result without blocking the executor
+    pub async fn sync_async<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut T) -> R + Send + 'static,
+    ) -> R {
+        let (tx, rx) = tokio::sync::oneshot::channel();
 
-        match Arc::try_unwrap(result).ok().and_then(|v| v.into_inner()) {
-            Some(v) => v,
-            None => {
-                unreachable!()
-            }
+        self.desync(move |v| {
+            tx.send(f(v)).ok();
+        });
+
+        rx.await
+            .expect("Desync thread stopped before applying the job")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_counter() {
+        let desync = Desync::new(0);
+        let handle = desync.handle().clone();
+        // Applied once the thread starts
+        handle.desync(|v| *v += 1);
+
+        let thread = desync.run();
+
+        let writers = (0..4)
+            .map(|_| {
+                let handle = handle.clone();
+                thread::spawn(move || {
+                    for _ in 0..100 {
+                        handle.desync(|v| *v += 1);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for writer in writers {
+            writer.join().unwrap();
         }
+
+        assert_eq!(handle.sync(|v| *v), 401);
+
+        drop(handle);
+        assert_eq!(thread.join().unwrap(), 401);
+    }
+
+    #[test]
+    fn moves_owned_values_into_jobs() {
+        /// Deliberately not `Clone`
+        struct Line(String);
+
+        let desync = Desync::new(Vec::new());
+        let handle = desync.handle().clone();
+        let thread = desync.run();
+
+        let line = Line("first".into());
+        handle.desync(move |lines| lines.push(line.0));
+        let line = Line("second".into());
+        let len = handle.sync(move |lines| {
+            lines.push(line.0);
+            lines.len()
+        });
+        assert_eq!(len, 2);
+
+        drop(handle);
+        assert_eq!(thread.join().unwrap(), ["first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn sync_from_async_task() {
+        let desync = Desync::new(String::from("Hello"));
+        let handle = desync.handle().clone();
+        let thread = desync.run();
+
+        handle.desync(|v| v.push_str(", World"));
+        let len = handle.sync_async(|v| v.len()).await;
+        assert_eq!(len, 12);
+
+        drop(handle);
+        assert_eq!(thread.join().unwrap(), "Hello, World");
     }
 }
@@ -1,4 +1,13 @@
+use flax::Entity;
 use thiserror::Error;
 
 #[derive(Error, Debug, Clone)]
-pub enum Error {}
+pub enum Error {
+    #[error("Widget {widget} exceeds the max fragment depth of {max_depth}")]
+    MaxDepthExceeded {
+        widget: &'static str,
+        max_depth: usize,
+    },
+    #[error("{child} is not a child of {parent}")]
+    NotAChild { parent: Entity, child: Entity },
+}
@@ -1,55 +1,511 @@
-use flax::{entity_ids, Component, ComponentValue, Entity, Query, World};
+use flax::{entity_ids, Component, Entity, FetchExt, Query, World};
 use futures_signals::signal::Mutable;
+use glam::Vec2;
 
-// pub trait EventHandler<T>: ComponentValue {
-//     fn on_event(&mut self, id: Entity, world: &World, event: &T);
-// }
-
-// impl<F, T> EventHandler<T> for F
-// where
-//     F: FnMut(Entity, &World, &T) + ComponentValue,
-// {
-//     fn on_event(&mut self, id: Entity, world: &World, event: &T) {
-//         (self)(id, world, event)
-//     }
-// }
-
-// impl<T> EventHandler<T> for flume::Sender<T>
-// where
-//     T: 'static + Send + Clone,
-// {
-//     fn on_event(&mut self, _: Entity, _: &World, event: &T) {
-//         self.send(event.clone()).ok();
-//     }
-// }
-
-// impl<T> EventHandler<T> for Mutable<T>
-// where
-//     T: 'static + Send + Sync + Clone,
-// {
-//     fn on_event(&mut self, _: Entity, _: &World, event: &T) {
-//         self.set(event.clone())
-//     }
-// }
-
-// impl<T> EventHandler<T> for futures_signals::signal::Sender<T>
-// where
-//     T: 'static + Send + Clone,
-// {
-//     fn on_event(&mut self, _: Entity, _: &World, event: &T) {
-//         self.send(event.clone()).ok();
-//     }
-// }
-
-pub type EventHook<T> = Box<dyn FnMut(Entity, &World, &T) + Send + Sync>;
-
-/// Send an event to all hooks in the world
-pub fn send_event<T: Sync>(world: &World, event: Component<EventHook<T>>, event_data: T)
+use crate::{
+    components::{disabled, focusable, focused, position, size, tab_index},
+    fragment::{children_of, parent_of},
+};
+
+/// Receives the events sent to a hook, see [`FragmentRef::on_event`](crate::fragment::FragmentRef::on_event).
+///
+/// Besides closures, events can be forwarded straight into a channel or a [`Mutable`].
+pub trait EventHandler<T>: 'static + Send + Sync {
+    fn on_event(&mut self, id: Entity, world: &World, event: &T) -> Propagation;
+}
+
+/// Closures may return a [`Propagation`], or nothing to let the event continue
+impl<F, T, R> EventHandler<T> for F
+where
+    F: 'static + FnMut(Entity, &World, &T) -> R + Send + Sync,
+    R: Into<Propagation>,
+{
+    fn on_event(&mut self, id: Entity, world: &World, event: &T) -> Propagation {
+        (self)(id, world, event).into()
+    }
+}
+
+/// Sends a clone of each event, dropping it if the receiver is gone
+impl<T> EventHandler<T> for flume::Sender<T>
+where
+    T: 'static + Send + Clone,
+{
+    fn on_event(&mut self, _: Entity, _: &World, event: &T) -> Propagation {
+        self.send(event.clone()).ok();
+        Propagation::Continue
+    }
+}
+
+/// Sets the value to a clone of each event
+impl<T> EventHandler<T> for Mutable<T>
+where
+    T: 'static + Send + Sync + Clone,
+{
+    fn on_event(&mut self, _: Entity, _: &World, event: &T) -> Propagation {
+        self.set(event.clone());
+        Propagation::Continue
+    }
+}
+
+pub type EventHook<T> = Box<dyn EventHandler<T>>;
+
+/// The hooks registered for an event on an entity, invoked in the order they were added
+pub type EventHooks<T> = Vec<EventHook<T>>;
+
+/// Whether an event continues to the ancestors of the entity which handled it.
+///
+/// Only [`send_event_bubbling`] stops early, [`send_event`] always reaches every hook.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Propagation {
+    /// The event is passed on to the next ancestor
+    #[default]
+    Continue,
+    /// The event was consumed
+    Stop,
+}
+
+/// Hooks which return nothing let the event continue
+impl From<()> for Propagation {
+    fn from(_: ()) -> Self {
+        Propagation::Continue
+    }
+}
+
+/// Send an event to all hooks in the world.
+///
+/// Entities which are [`disabled`] are skipped.
+///
+/// Returns the number of hooks which were invoked, so that the caller can fall back to a default
+/// when nothing handled the event.
+pub fn send_event<T: Sync>(world: &World, event: Component<EventHooks<T>>, event_data: T) -> usize
 where
     EventHook<T>: 'static,
 {
     Query::new((entity_ids(), event.as_mut()))
+        .without(disabled())
+        .borrow(world)
+        .iter()
+        .map(|(id, hooks)| {
+            for hook in hooks.iter_mut() {
+                hook.on_event(id, world, &event_data);
+            }
+
+            hooks.len()
+        })
+        .sum()
+}
+
+/// Send an event to the hooks of a single entity.
+///
+/// Returns true if the entity had a hook for the event. A [`disabled`] entity is skipped like in
+/// [`send_event`].
+pub fn send_event_to<T: Sync>(
+    world: &World,
+    id: Entity,
+    event: Component<EventHooks<T>>,
+    event_data: T,
+) -> bool
+where
+    EventHook<T>: 'static,
+{
+    if world.has(id, disabled()) {
+        return false;
+    }
+
+    let Ok(mut hooks) = world.get_mut(id, event) else {
+        return false;
+    };
+
+    for hook in hooks.iter_mut() {
+        hook.on_event(id, world, &event_data);
+    }
+
+    !hooks.is_empty()
+}
+
+/// Send an event to `target` and then to each of its ancestors in turn, such as a key press to
+/// the focused widget.
+///
+/// Bubbling stops at the first hook which returns [`Propagation::Stop`], and the remaining hooks
+/// of the same entity are not invoked either. Entities which are
+/// [`disabled`] or lack the hook are passed over.
+///
+/// Returns true if the event was consumed.
+pub fn send_event_bubbling<T: Sync>(
+    world: &World,
+    target: Entity,
+    event: Component<EventHooks<T>>,
+    event_data: T,
+) -> bool
+where
+    EventHook<T>: 'static,
+{
+    let mut current = Some(target);
+    while let Some(id) = current {
+        if !world.has(id, disabled()) {
+            if let Ok(mut hooks) = world.get_mut(id, event) {
+                for hook in hooks.iter_mut() {
+                    if hook.on_event(id, world, &event_data) == Propagation::Stop {
+                        return true;
+                    }
+                }
+            }
+        }
+
+        current = parent_of(world, id);
+    }
+
+    false
+}
+
+/// Returns the widget which has keyboard focus, if any
+pub fn focused_entity(world: &World) -> Option<Entity> {
+    Query::new(entity_ids())
+        .with(focused())
+        .borrow(world)
+        .iter()
+        .next()
+}
+
+/// Gives keyboard focus to `id`, taking it from the previously [`focused`] widget, so that at
+/// most one widget has focus at a time.
+pub fn set_focus(world: &mut World, id: Entity) {
+    if let Some(current) = focused_entity(world).filter(|&current| current != id) {
+        world.remove(current, focused()).unwrap();
+    }
+
+    world.set(id, focused(), ()).unwrap();
+}
+
+/// A direction on screen, used for spatial navigation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl FocusDirection {
+    /// Splits `delta` into the distance along the direction and the distance across it
+    fn project(self, delta: Vec2) -> (f32, f32) {
+        match self {
+            FocusDirection::Up => (-delta.y, delta.x.abs()),
+            FocusDirection::Down => (delta.y, delta.x.abs()),
+            FocusDirection::Left => (-delta.x, delta.y.abs()),
+            FocusDirection::Right => (delta.x, delta.y.abs()),
+        }
+    }
+}
+
+/// Moves focus from `current` to the nearest [`focusable`] widget in `dir`, such as when an arrow
+/// key is pressed.
+///
+/// Widgets are compared by the center of their [`position`] and [`size`]. Only widgets whose
+/// center lies beyond the center of `current` are considered. Ties in distance are resolved in
+/// reading order, top to bottom and then left to right.
+///
+/// Returns the newly focused widget, or `None` if there is no widget in that direction, in which
+/// case focus is left as is.
+pub fn focus_direction(world: &mut World, current: Entity, dir: FocusDirection) -> Option<Entity> {
+    let center = |pos: Vec2, size: Option<&Vec2>| pos + size.copied().unwrap_or_default() / 2.0;
+    let from = center(
+        *world.get(current, position()).ok()?,
+        world.get(current, size()).ok().as_deref(),
+    );
+
+    let (target, _, _) = Query::new((entity_ids(), position(), size().opt()))
+        .with(focusable())
+        .without(disabled())
+        .borrow(world)
+        .iter()
+        .filter(|&(id, _, _)| id != current)
+        .filter_map(|(id, &pos, size)| {
+            let (along, across) = dir.project(center(pos, size) - from);
+            (along > 0.0).then(|| (id, along.hypot(across), pos))
+        })
+        .min_by(|(_, a, a_pos), (_, b, b_pos)| {
+            a.total_cmp(b)
+                .then_with(|| a_pos.y.total_cmp(&b_pos.y))
+                .then_with(|| a_pos.x.total_cmp(&b_pos.x))
+        })?;
+
+    set_focus(world, target);
+
+    Some(target)
+}
+
+/// Returns the position of `id` in a depth first traversal of its tree, as the root and the
+/// index among the siblings at each level
+fn tree_order(world: &World, mut id: Entity) -> (Entity, Vec<usize>) {
+    let mut path = Vec::new();
+    while let Some(parent) = parent_of(world, id) {
+        let index = children_of(world, parent)
+            .iter()
+            .position(|&child| child == id)
+            .unwrap_or_default();
+
+        path.push(index);
+        id = parent;
+    }
+
+    path.reverse();
+    (id, path)
+}
+
+/// Moves focus to the next [`focusable`] widget, such as when Tab is pressed, wrapping around
+/// after the last.
+///
+/// Widgets are focused in ascending [`tab_index`], and in tree order among those with the same
+/// index. Widgets with a negative index are skipped.
+///
+/// Returns the newly focused widget.
+pub fn focus_next(world: &mut World) -> Option<Entity> {
+    let current = focused_entity(world);
+
+    let mut candidates = Query::new((entity_ids(), tab_index().opt_or(0)))
+        .with(focusable())
+        .without(disabled())
         .borrow(world)
         .iter()
-        .for_each(|(id, handler)| handler(id, world, &event_data))
+        .filter(|&(_, &index)| index >= 0)
+        .map(|(id, &index)| (index, id))
+        .collect::<Vec<_>>();
+
+    candidates.sort_by_cached_key(|&(index, id)| (index, tree_order(world, id)));
+
+    let next = match current.and_then(|id| candidates.iter().position(|&(_, v)| v == id)) {
+        Some(i) => candidates[(i + 1) % candidates.len()].1,
+        None => candidates.first()?.1,
+    };
+
+    set_focus(world, next);
+
+    Some(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
+    use flax::{child_of, component, EntityBuilder};
+
+    use super::*;
+
+    component! {
+        on_click: EventHooks<()>,
+        on_input: EventHooks<u32>,
+    }
+
+    #[test]
+    fn disabled_skips_hooks() {
+        let mut world = World::new();
+
+        let clicked = Arc::new(AtomicUsize::new(0));
+        let hook = |clicked: &Arc<AtomicUsize>, value| -> EventHook<()> {
+            let clicked = clicked.clone();
+            Box::new(move |_, _: &World, _: &()| {
+                clicked.fetch_add(value, Ordering::Relaxed);
+                Propagation::Continue
+            })
+        };
+
+        EntityBuilder::new()
+            .set(on_click(), vec![hook(&clicked, 1)])
+            .spawn(&mut world);
+
+        EntityBuilder::new()
+            .set(on_click(), vec![hook(&clicked, 10)])
+            .tag(disabled())
+            .spawn(&mut world);
+
+        assert_eq!(send_event(&world, on_click(), ()), 1);
+        assert_eq!(clicked.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn send_to_single_entity() {
+        let mut world = World::new();
+
+        let clicked = Arc::new(AtomicUsize::new(0));
+        let hook = {
+            let clicked = clicked.clone();
+            move |_, _: &World, _: &()| {
+                clicked.fetch_add(1, Ordering::Relaxed);
+            }
+        };
+
+        let button = EntityBuilder::new()
+            .set(on_click(), vec![Box::new(hook.clone()) as EventHook<_>])
+            .spawn(&mut world);
+        EntityBuilder::new()
+            .set(on_click(), vec![Box::new(hook) as EventHook<_>])
+            .spawn(&mut world);
+        let label = world.spawn();
+
+        assert!(send_event_to(&world, button, on_click(), ()));
+        assert!(!send_event_to(&world, label, on_click(), ()));
+        assert_eq!(clicked.load(Ordering::Relaxed), 1);
+
+        assert_eq!(send_event(&world, on_click(), ()), 2);
+    }
+
+    #[test]
+    fn sender_forwards_events() {
+        let mut world = World::new();
+
+        let (tx, rx) = flume::unbounded::<u32>();
+        let dropped = flume::unbounded::<u32>().0;
+        EntityBuilder::new()
+            .set(
+                on_input(),
+                vec![Box::new(tx) as EventHook<_>, Box::new(dropped)],
+            )
+            .spawn(&mut world);
+
+        send_event(&world, on_input(), 1);
+        send_event(&world, on_input(), 2);
+
+        assert_eq!(rx.drain().collect::<Vec<_>>(), [1, 2]);
+    }
+
+    #[test]
+    fn mutable_follows_events() {
+        let mut world = World::new();
+
+        let value = Mutable::new(0u32);
+        EntityBuilder::new()
+            .set(on_input(), vec![Box::new(value.clone()) as EventHook<_>])
+            .spawn(&mut world);
+
+        send_event(&world, on_input(), 5);
+
+        assert_eq!(value.get(), 5);
+    }
+
+    #[test]
+    fn bubbling_stops_when_consumed() {
+        let mut world = World::new();
+
+        let handled = Arc::new(Mutex::new(Vec::new()));
+        let hook = |name, propagation| -> EventHook<()> {
+            let handled = handled.clone();
+            Box::new(move |_, _: &World, _: &()| {
+                handled.lock().unwrap().push(name);
+                propagation
+            })
+        };
+
+        let root = EntityBuilder::new()
+            .set(on_click(), vec![hook("root", Propagation::Continue)])
+            .spawn(&mut world);
+        let panel = EntityBuilder::new()
+            .tag(child_of(root))
+            .set(on_click(), vec![hook("panel", Propagation::Stop)])
+            .spawn(&mut world);
+        let row = EntityBuilder::new().tag(child_of(panel)).spawn(&mut world);
+        let button = EntityBuilder::new()
+            .tag(child_of(row))
+            .set(on_click(), vec![hook("button", Propagation::Continue)])
+            .spawn(&mut world);
+        // Not an ancestor of the button
+        EntityBuilder::new()
+            .tag(child_of(root))
+            .set(on_click(), vec![hook("sibling", Propagation::Continue)])
+            .spawn(&mut world);
+
+        assert!(send_event_bubbling(&world, button, on_click(), ()));
+        assert_eq!(*handled.lock().unwrap(), ["button", "panel"]);
+
+        handled.lock().unwrap().clear();
+        world.set(panel, disabled(), ()).unwrap();
+        assert!(!send_event_bubbling(&world, button, on_click(), ()));
+        assert_eq!(*handled.lock().unwrap(), ["button", "root"]);
+    }
+
+    #[test]
+    fn focus_is_exclusive() {
+        let mut world = World::new();
+        let [a, b, c] = [(); 3].map(|_| world.spawn());
+        assert_eq!(focused_entity(&world), None);
+
+        set_focus(&mut world, a);
+        assert_eq!(focused_entity(&world), Some(a));
+
+        set_focus(&mut world, c);
+        set_focus(&mut world, c);
+        assert_eq!(focused_entity(&world), Some(c));
+        assert!(!world.has(a, focused()));
+        assert!(!world.has(b, focused()));
+    }
+
+    #[test]
+    fn focus_grid_neighbors() {
+        let mut world = World::new();
+
+        let grid = [(0.0, 0.0), (10.0, 0.0), (0.0, 5.0), (10.0, 5.0)].map(|(x, y)| {
+            EntityBuilder::new()
+                .set(position(), Vec2::new(x, y))
+                .set(size(), Vec2::new(5.0, 1.0))
+                .tag(focusable())
+                .spawn(&mut world)
+        });
+        let [top_left, top_right, bottom_left, bottom_right] = grid;
+        world.set(top_left, focused(), ()).unwrap();
+
+        assert_eq!(
+            focus_direction(&mut world, top_left, FocusDirection::Up),
+            None
+        );
+        assert!(world.has(top_left, focused()));
+
+        assert_eq!(
+            focus_direction(&mut world, top_left, FocusDirection::Right),
+            Some(top_right)
+        );
+        assert_eq!(
+            focus_direction(&mut world, top_right, FocusDirection::Down),
+            Some(bottom_right)
+        );
+        assert_eq!(
+            focus_direction(&mut world, bottom_right, FocusDirection::Left),
+            Some(bottom_left)
+        );
+
+        let focused = Query::new(entity_ids())
+            .with(focused())
+            .borrow(&world)
+            .iter()
+            .collect::<Vec<_>>();
+        assert_eq!(focused, [bottom_left]);
+    }
+
+    #[test]
+    fn tab_follows_index() {
+        let mut world = World::new();
+
+        let root = world.spawn();
+        let [name, email, submit, hidden] = [0, 2, 1, -1].map(|index| {
+            EntityBuilder::new()
+                .tag(child_of(root))
+                .tag(focusable())
+                .set(tab_index(), index)
+                .spawn(&mut world)
+        });
+        // Ties are broken by tree order
+        let notes = EntityBuilder::new()
+            .tag(child_of(root))
+            .tag(focusable())
+            .spawn(&mut world);
+
+        let order = (0..6)
+            .map(|_| focus_next(&mut world).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(order, [name, notes, submit, email, name, notes]);
+        assert!(!world.has(hidden, focused()));
+    }
 }
@@ -1,11 +1,173 @@
-use std::sync::MutexGuard;
+use std::{
+    any::{type_name, Any},
+    ops::DerefMut,
+    pin::Pin,
+    sync::MutexGuard,
+    task::{Context, Poll},
+    time::Duration,
+};
 
-use flax::{child_of, Component, ComponentValue, Entity, World};
+use flax::events::ChangeSubscriber;
+use flax::{
+    child_of, component, components::name, entity_ids, relations_like, Component, ComponentKey,
+    ComponentValue, Entity, EntityBuilder, FetchExt, Query, World,
+};
+use futures::{future::BoxFuture, Future, FutureExt, StreamExt};
+use futures_signals::signal::{Signal, SignalExt};
+use glam::Vec2;
+use itertools::Itertools;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 
 use crate::{
-    app::AppRef, components::widget, events::EventHook, BoxedWidget, Widget, WidgetFuture,
+    accessibility::Role,
+    app::{
+        despawn_callbacks, take_despawn_callbacks, AppRef, DespawnCallback, Event, Persisted,
+        WeakAppRef,
+    },
+    components::{
+        aria_label, constraints, headless, hidden, order, persistent, position, viewport, widget,
+    },
+    error::Error,
+    events::{EventHandler, EventHooks},
+    notify::{self, NotifyReceiver},
+    StatefulWidget, SyncWidget, Widget, WidgetFuture,
 };
 
+/// How long a task spawned by [`Fragment::spawn_task`] may run after being cancelled before it is
+/// aborted
+pub const TASK_GRACE_PERIOD: Duration = Duration::from_millis(500);
+
+component! {
+    tasks: Vec<FragmentTask>,
+    /// Removed again by the same [`FragmentRef::clear_components`]
+    clearing: (),
+    /// Key of the last [`Fragment::put_keyed`]
+    put_key: Box<dyn Any + Send + Sync>,
+}
+
+/// See [`Fragment::signal`]
+pub struct ComponentSignal<T> {
+    app: AppRef,
+    rx: NotifyReceiver,
+    changed: Box<ChangeFn<T>>,
+}
+
+type ChangeFn<T> = dyn FnMut(&World) -> Change<T> + Send;
+
+enum Change<T> {
+    Changed(T),
+    Unchanged,
+    Despawned,
+}
+
+impl<T> Signal for ComponentSignal<T> {
+    type Item = T;
+
+    fn poll_change(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        loop {
+            let this = &mut *self;
+            match (this.changed)(&this.app.world()) {
+                Change::Changed(value) => return Poll::Ready(Some(value)),
+                Change::Unchanged => {}
+                Change::Despawned => return Poll::Ready(None),
+            }
+
+            futures::ready!(self.rx.poll_unpin(cx));
+        }
+    }
+}
+
+/// A task tied to the lifetime of a fragment
+struct FragmentTask {
+    token: CancellationToken,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for FragmentTask {
+    fn drop(&mut self) {
+        self.token.cancel();
+
+        let Some(mut handle) = self.handle.take() else {
+            return;
+        };
+
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn(async move {
+                    if tokio::time::timeout(TASK_GRACE_PERIOD, &mut handle)
+                        .await
+                        .is_err()
+                    {
+                        handle.abort()
+                    }
+                });
+            }
+            Err(_) => handle.abort(),
+        }
+    }
+}
+
+/// A weak handle to a [`Fragment`].
+///
+/// Unlike an [`AppRef`], it does not keep the world alive, so it can be stored in hooks on the
+/// world without creating a reference cycle.
+#[derive(Debug, Clone)]
+pub struct WeakFragment {
+    id: Entity,
+    app: WeakAppRef,
+}
+
+impl WeakFragment {
+    pub fn id(&self) -> Entity {
+        self.id
+    }
+
+    /// Sets a component on the fragment.
+    ///
+    /// The change is deferred to the event loop, as hooks run while the world is locked, and is
+    /// discarded if the fragment has since been despawned. Returns false if the app is gone.
+    pub fn upgrade_set<T: ComponentValue>(&self, component: Component<T>, value: T) -> bool {
+        match self.app.upgrade() {
+            Some(app) => app
+                .enqueue(Event::set_component(self.id, component, value))
+                .is_ok(),
+            None => false,
+        }
+    }
+}
+
+/// Pushes new props into a mounted [`StatefulWidget`].
+///
+/// The widget's fragment lives as long as the handle, and is despawned when the handle is
+/// dropped.
+pub struct PropsHandle<P> {
+    fragment: Fragment,
+    widget: Box<dyn StatefulWidget<Props = P>>,
+}
+
+impl<P> PropsHandle<P> {
+    pub fn id(&self) -> Entity {
+        self.fragment.id
+    }
+
+    /// Renders the widget again with `props`, on the same fragment
+    pub fn update(&mut self, props: P) {
+        self.widget.update(&mut self.fragment.write(), props)
+    }
+}
+
+impl<P> Drop for PropsHandle<P> {
+    fn drop(&mut self) {
+        self.fragment
+            .app
+            .enqueue(Event::Despawn(self.fragment.id))
+            .ok();
+    }
+}
+
 /// Represents a piece of the UI
 pub struct Fragment {
     id: Entity,
@@ -14,36 +176,245 @@ pub struct Fragment {
 
 impl Fragment {
     pub(crate) fn spawn(world: &mut World, app: AppRef, parent: Option<Entity>) -> Fragment {
-        let mut builder = Entity::builder();
+        Self::spawn_with(world, app, parent, Entity::builder())
+    }
 
+    /// Spawns a fragment with the components of `builder` in place from the start.
+    ///
+    /// Adding components to a child after it is spawned leaves transitions between the
+    /// archetypes of its parent's children, which trip up flax when the parent is later
+    /// despawned.
+    fn spawn_with(
+        world: &mut World,
+        app: AppRef,
+        parent: Option<Entity>,
+        mut builder: EntityBuilder,
+    ) -> Fragment {
         builder.tag(widget());
         if let Some(parent) = parent {
             builder.tag(child_of(parent));
         }
 
         let id = builder.spawn(world);
+        app.lifecycle.mount(id);
 
         Fragment { id, app }
     }
 
+    /// Returns the screen space position of `id`.
+    ///
+    /// See [`absolute_position`](crate::layout::absolute_position).
+    pub fn absolute_position(&self, id: Entity) -> Vec2 {
+        crate::layout::absolute_position(&self.app.world(), id)
+    }
+
+    /// Returns the dotted path of names from the root to this fragment, which identifies it in
+    /// diagnostics and saved state. See [`save_state`](crate::app::save_state).
+    pub fn name_path(&self) -> String {
+        name_path(&self.app.world(), self.id)
+    }
+
+    /// Returns another handle to this fragment, such as for mounting a new widget in place of the
+    /// current one
+    pub(crate) fn duplicate(&self) -> Fragment {
+        Fragment {
+            id: self.id,
+            app: self.app.clone(),
+        }
+    }
+
+    /// Returns a handle to this fragment which can be captured by event hooks
+    pub fn downgrade(&self) -> WeakFragment {
+        WeakFragment {
+            id: self.id,
+            app: self.app.downgrade(),
+        }
+    }
+
+    /// Returns a signal of the component's value on this fragment.
+    ///
+    /// The current value is yielded first, if any, followed by each change. Several changes
+    /// between polls yield only the latest value. The signal ends once the fragment is despawned.
+    pub fn signal<T: ComponentValue + Clone>(&self, component: Component<T>) -> ComponentSignal<T> {
+        let (tx, rx) = notify::channel();
+        self.app
+            .world()
+            .subscribe(ChangeSubscriber::new(&[component.key()], tx));
+
+        let mut query = Query::new(component)
+            .filter(component.modified() | component.inserted())
+            .entity(self.id);
+
+        ComponentSignal {
+            app: self.app.clone(),
+            rx,
+            changed: Box::new(move |world| match query.borrow(world).get() {
+                Ok(value) => Change::Changed(value.clone()),
+                Err(flax::Error::NoSuchEntity(_)) => Change::Despawned,
+                Err(_) => Change::Unchanged,
+            }),
+        }
+    }
+
     /// Acquire a lock to the world to modify the fragment
-    pub fn write(&mut self) -> FragmentRef {
+    pub fn write(&mut self) -> FragmentRef<'_> {
         FragmentRef {
-            world: self.app.world(),
+            world: Some(self.app.world()),
             fragment: self,
+            despawned: Vec::new(),
         }
     }
 
+    /// Applies several writes to the fragment as one logical update.
+    ///
+    /// Change handlers wrapped in [`Coalesced`](crate::batch::Coalesced) are invoked at most once,
+    /// after `f` returns, rather than once per write. This includes those of the layout widgets,
+    /// the [`DebugOverlay`](crate::widgets::DebugOverlay) and the terminal renderer, so a batch is
+    /// laid out and redrawn once.
+    pub fn batch<R>(&mut self, f: impl FnOnce(&mut FragmentRef) -> R) -> R {
+        crate::batch::scope(|| f(&mut self.write()))
+    }
+
     /// Render a widget in this fragment.
     ///
-    /// This is used to yield a whole widget to the fragment
+    /// This is used to yield a whole widget to the fragment. The fragment is cleared of the
+    /// children and components left by the previous widget first, so repeatedly putting a widget
+    /// replaces it rather than piling up children.
+    ///
+    /// Cancelling the returned future drops the children the widget attached, despawning their
+    /// subtrees deepest first.
     pub async fn put<W: Widget>(&mut self, widget: W) -> W::Output {
-        widget
-            .mount(Self {
-                id: self.id,
-                app: self.app().clone(),
-            })
-            .await
+        self.write().clear();
+        widget.mount(self.duplicate()).await
+    }
+
+    /// Puts a widget in this fragment like [`Self::put`], unless the previous call had the same
+    /// `key`, in which case the current content is kept and `None` is returned.
+    ///
+    /// The key is stored on the fragment, and forgotten when the fragment is cleared by anything
+    /// else, such as a plain [`Self::put`]. A key of another type never equals the previous one.
+    pub async fn put_keyed<K, W>(&mut self, key: K, widget: W) -> Option<W::Output>
+    where
+        K: 'static + Eq + Send + Sync,
+        W: Widget,
+    {
+        {
+            let id = self.id;
+            let mut fragment = self.write();
+            let unchanged = fragment
+                .world()
+                .get(id, put_key())
+                .is_ok_and(|prev| prev.downcast_ref::<K>() == Some(&key));
+            if unchanged {
+                return None;
+            }
+
+            fragment.clear().set(put_key(), Box::new(key));
+        }
+
+        Some(widget.mount(self.duplicate()).await)
+    }
+
+    /// Constructs a widget on the blocking thread pool, and then mounts it in this fragment.
+    ///
+    /// Only `make` runs off the async executor, which allows it to do CPU heavy work such as
+    /// parsing or formatting large content. The widget is mounted like [`Self::put`] on the
+    /// calling task, so the fragment is not touched from the blocking thread.
+    ///
+    /// # Panics
+    /// If `make` panics, the panic is resumed here.
+    pub async fn put_blocking<W>(&mut self, make: impl FnOnce() -> W + Send + 'static) -> W::Output
+    where
+        W: 'static + Widget,
+    {
+        let widget = match tokio::task::spawn_blocking(make).await {
+            Ok(widget) => widget,
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        };
+
+        self.put(widget).await
+    }
+
+    /// Mounts a widget in this fragment like [`Self::put`], but gives up once `deadline` has
+    /// passed.
+    ///
+    /// On timeout the widget is cancelled and the fragment cleared of everything it left behind,
+    /// including its children. The output of `on_timeout` is returned instead.
+    pub async fn put_with_deadline<W: Widget>(
+        &mut self,
+        widget: W,
+        deadline: Duration,
+        on_timeout: impl FnOnce() -> W::Output,
+    ) -> W::Output {
+        match tokio::time::timeout(deadline, self.put(widget)).await {
+            Ok(output) => output,
+            Err(_) => {
+                self.write().clear();
+                on_timeout()
+            }
+        }
+    }
+
+    /// Mounts each widget into this fragment in turn, awaiting one before the next.
+    ///
+    /// The fragment is cleared between steps, so each widget starts out fresh.
+    pub async fn put_sequence(&mut self, widgets: Vec<Box<dyn Widget<Output = ()> + Send>>) {
+        for widget in widgets {
+            self.put(widget).await;
+        }
+    }
+
+    /// Attaches a stateful widget as a child, rendered with the initial `props`.
+    ///
+    /// Unlike [`Self::attach`], the returned handle allows application code to push new props
+    /// into the widget imperatively, without despawning it.
+    pub fn mount_stateful_handle<W>(
+        &mut self,
+        mut widget: W,
+        props: W::Props,
+    ) -> PropsHandle<W::Props>
+    where
+        W: 'static + StatefulWidget,
+    {
+        let mut fragment = self.spawn_child(None);
+        widget.update(&mut fragment.write(), props);
+
+        PropsHandle {
+            fragment,
+            widget: Box::new(widget),
+        }
+    }
+
+    /// Mounts a synchronous widget inline, without boxing or polling a future.
+    pub fn put_sync<W: SyncWidget>(&mut self, widget: W) {
+        widget.mount_sync(&mut self.write())
+    }
+
+    /// Spawns a task which lives as long as the fragment.
+    ///
+    /// When the fragment is despawned or cleared the task's cancellation token is triggered,
+    /// giving the task [`TASK_GRACE_PERIOD`] to stop gracefully before it is aborted.
+    ///
+    /// # Panics
+    ///
+    /// If called outside of a Tokio runtime.
+    pub fn spawn_task<F, Fut>(&mut self, task: F)
+    where
+        F: FnOnce(CancellationToken) -> Fut,
+        Fut: 'static + Future<Output = ()> + Send,
+    {
+        let token = CancellationToken::new();
+        let handle = tokio::spawn(task(token.clone()));
+
+        self.app
+            .world()
+            .entry(self.id, tasks())
+            .unwrap()
+            .or_default()
+            .push(FragmentTask {
+                token,
+                handle: Some(handle),
+            });
     }
 
     // Returns a handle used to control the app
@@ -51,28 +422,174 @@ impl Fragment {
         &self.app
     }
 
-    /// Attach another fragment as a child
+    /// Attach another fragment as a child.
+    ///
+    /// The returned future is `Send`, and `'static` for a `'static` widget, so it can be passed
+    /// to `tokio::spawn` as is.
+    ///
+    /// If the child would be nested deeper than the app allows, see
+    /// [`App::with_max_depth`](crate::app::App::with_max_depth), it is despawned and the returned
+    /// future never completes. Use [`Self::try_attach`] to handle that case.
     pub fn attach<'w, W>(&mut self, widget: W) -> WidgetFuture<'w, W::Output>
     where
         W: 'w + Widget,
     {
-        let app = self.app.clone();
-        let id = self.id;
-        let child = Fragment::spawn(&mut self.app.world(), app, Some(id));
+        let child = self.spawn_child(None);
+        WidgetFuture::new(
+            child.id,
+            self.app.clone(),
+            Some(type_name::<W>()),
+            self.mount_within_depth(widget, child),
+        )
+    }
 
-        WidgetFuture::new(child.id, widget.mount(child))
+    /// Attach another fragment as a child at `index` among its siblings.
+    ///
+    /// Siblings at or after `index` are moved back one step to make room. Past the max depth, the
+    /// returned future never completes, like that of [`Self::attach`].
+    pub fn attach_at<'w, W>(&mut self, index: u32, widget: W) -> WidgetFuture<'w, W::Output>
+    where
+        W: 'w + Widget,
+    {
+        let child = self.spawn_child(Some(index));
+        WidgetFuture::new(
+            child.id,
+            self.app.clone(),
+            Some(type_name::<W>()),
+            self.mount_within_depth(widget, child),
+        )
     }
 
     /// Attach another fragment as a child
     pub fn attach_boxed<'w, W>(&mut self, widget: Box<W>) -> WidgetFuture<'w, W::Output>
     where
         W: 'w + Widget + ?Sized,
+        W::Output: Send + 'static,
     {
-        let app = self.app.clone();
-        let id = self.id;
-        let child = Fragment::spawn(&mut self.app.world(), app, Some(id));
+        let child = self.spawn_child(None);
+        WidgetFuture::new(
+            child.id,
+            self.app.clone(),
+            Some(type_name::<W>()),
+            self.mount_within_depth(widget, child),
+        )
+    }
+
+    /// Attach another fragment as a child, unless it would be nested deeper than the max depth of
+    /// the app.
+    ///
+    /// Unlike [`Self::attach`], nothing is spawned when the depth is exceeded, and the error is
+    /// returned right away so the caller can carry on without the child.
+    pub fn try_attach<'w, W>(&mut self, widget: W) -> Result<WidgetFuture<'w, W::Output>, Error>
+    where
+        W: 'w + Widget,
+    {
+        let depth = depth_of(&self.app.world(), self.id) + 1;
+        self.check_depth::<W>(depth)?;
+        Ok(self.attach(widget))
+    }
 
-        WidgetFuture::new(child.id, widget.mount_boxed(child))
+    /// Fails if a widget mounted at `depth` is nested deeper than the max depth of the app
+    fn check_depth<W: ?Sized>(&self, depth: usize) -> Result<(), Error> {
+        match self.app.max_depth {
+            Some(max_depth) if depth > max_depth => Err(Error::MaxDepthExceeded {
+                widget: type_name::<W>(),
+                max_depth,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Mounts `widget` into `child`, unless the child is nested deeper than the max depth of the
+    /// app, in which case the child is despawned and the widget is never mounted
+    fn mount_within_depth<'w, W>(&self, widget: W, child: Fragment) -> BoxFuture<'w, W::Output>
+    where
+        W: 'w + Widget,
+    {
+        let depth = depth_of(&self.app.world(), child.id);
+        if let Err(err) = self.check_depth::<W>(depth) {
+            tracing::error!(id = ?child.id, "{err}");
+            self.app.enqueue(Event::Despawn(child.id)).ok();
+            // There is no output to resolve to, see `App::with_max_depth`
+            return Box::pin(std::future::pending());
+        }
+
+        let span = tracing::debug_span!("mount", path = name_path(&self.app.world(), child.id));
+        widget.mount(child).instrument(span).boxed()
+    }
+
+    /// Spawns a child fragment, placed at `index` or after the last sibling
+    fn spawn_child(&mut self, index: Option<u32>) -> Fragment {
+        let mut world = self.app.world();
+        let mut siblings = Query::new(order().as_mut()).with(child_of(self.id));
+
+        let index = match index {
+            Some(index) => {
+                for order in &mut siblings.borrow(&world) {
+                    if *order >= index {
+                        *order += 1;
+                    }
+                }
+                index
+            }
+            None => siblings
+                .borrow(&world)
+                .iter()
+                .map(|order| *order + 1)
+                .max()
+                .unwrap_or_default(),
+        };
+
+        let mut builder = Entity::builder();
+        builder.set(order(), index);
+        Fragment::spawn_with(&mut world, self.app.clone(), Some(self.id), builder)
+    }
+
+    /// Removes the child `id` along with its subtree, leaving the other children in place.
+    ///
+    /// The despawn is applied by the event loop. Fragments do not keep track of the widgets
+    /// mounted in their children, so the [`WidgetFuture`] of the child should be dropped as well.
+    ///
+    /// Returns an error if `id` is not a child of this fragment, such as when it was already
+    /// despawned.
+    pub fn detach(&mut self, id: Entity) -> Result<(), Error> {
+        if parent_of(&self.app.world(), id) != Some(self.id) {
+            return Err(Error::NotAChild {
+                parent: self.id,
+                child: id,
+            });
+        }
+
+        self.app.enqueue(Event::Despawn(id)).ok();
+        Ok(())
+    }
+
+    /// Returns the fragment this one is attached to, or `None` for a root fragment
+    pub fn parent(&self) -> Option<Entity> {
+        parent_of(&self.app.world(), self.id)
+    }
+
+    /// Returns the children of this fragment in ascending [`order`].
+    pub fn children(&self) -> Vec<Entity> {
+        children_of(&self.app.world(), self.id)
+    }
+
+    /// Sets the accessible name of the fragment
+    pub fn set_label(&mut self, label: impl Into<String>) -> &mut Self {
+        self.write().set(aria_label(), label.into());
+        self
+    }
+
+    /// Sets the outcome of validating the fragment's input, such as of a form field
+    pub fn set_validation(&mut self, result: Result<(), String>) -> &mut Self {
+        self.write().set(crate::components::validation(), result);
+        self
+    }
+
+    /// Sets the semantic role of the fragment
+    pub fn set_role(&mut self, role: Role) -> &mut Self {
+        self.write().set(crate::components::role(), role);
+        self
     }
 
     pub fn id(&self) -> Entity {
@@ -80,41 +597,1065 @@ impl Fragment {
     }
 }
 
+/// Returns the parent of `id`, or `None` for a root
+pub(crate) fn parent_of(world: &World, id: Entity) -> Option<Entity> {
+    Query::new(relations_like(child_of))
+        .borrow(world)
+        .get(id)
+        .ok()?
+        .next()
+        .map(|(parent, _)| parent)
+}
+
+/// Returns the dotted path of `id` from the root, e.g; `Application.Row.Clock`.
+///
+/// Each fragment along the path is called by its [`name`], or otherwise by its index among its
+/// siblings. An unnamed root is left out.
+pub(crate) fn name_path(world: &World, id: Entity) -> String {
+    let mut segments = Vec::new();
+    let mut current = Some(id);
+    while let Some(id) = current {
+        let parent = parent_of(world, id);
+        match (world.get(id, name()), parent) {
+            (Ok(name), _) => segments.push(name.clone()),
+            (Err(_), Some(parent)) => {
+                let index = children_of(world, parent).iter().position(|&v| v == id);
+                segments.push(index.unwrap_or_default().to_string())
+            }
+            (Err(_), None) => {}
+        }
+
+        current = parent;
+    }
+
+    segments.reverse();
+    segments.join(".")
+}
+
+/// Returns the number of ancestors of `id`
+pub(crate) fn depth_of(world: &World, mut id: Entity) -> usize {
+    let mut depth = 0;
+    while let Some(parent) = parent_of(world, id) {
+        id = parent;
+        depth += 1;
+    }
+
+    depth
+}
+
+/// Returns the topmost ancestor of `id`, which is `id` itself for a root
+pub(crate) fn root_of(world: &World, mut id: Entity) -> Entity {
+    while let Some(parent) = parent_of(world, id) {
+        id = parent
+    }
+
+    id
+}
+
+/// Returns true if `id` or any of its ancestors is [`hidden`]
+pub(crate) fn is_hidden(world: &World, mut id: Entity) -> bool {
+    loop {
+        if world.has(id, hidden()) {
+            return true;
+        }
+
+        match parent_of(world, id) {
+            Some(parent) => id = parent,
+            None => return false,
+        }
+    }
+}
+
+/// Returns the children of `id` by ascending [`order`].
+///
+/// Children without an order come last, in the order they were spawned.
+pub(crate) fn children_of(world: &World, id: Entity) -> Vec<Entity> {
+    Query::new((entity_ids(), order().opt_or(u32::MAX)))
+        .with(child_of(id))
+        .borrow(world)
+        .iter()
+        .sorted_by_key(|&(id, &order)| (order, id))
+        .map(|(id, _)| id)
+        .collect()
+}
+
 pub struct FragmentRef<'a> {
-    world: MutexGuard<'a, World>,
+    /// Only taken when dropped, to unlock the world before running the despawn callbacks
+    world: Option<MutexGuard<'a, World>>,
     fragment: &'a Fragment,
+    /// Despawn callbacks of the descendants removed by [`Self::clear`]
+    despawned: Vec<(Entity, DespawnCallback)>,
 }
 
 impl<'a> FragmentRef<'a> {
     pub fn world(&self) -> &World {
-        &self.world
+        self.world.as_ref().unwrap()
     }
 
     pub fn world_mut(&mut self) -> &mut World {
-        &mut self.world
+        self.world.as_mut().unwrap()
+    }
+
+    /// Returns a clone of the component value, or `None` if the fragment does not have it
+    pub fn get<T: ComponentValue + Clone>(&self, component: Component<T>) -> Option<T> {
+        self.world()
+            .get(self.fragment.id, component)
+            .ok()
+            .map(|v| v.clone())
+    }
+
+    /// Returns a copy of the component value, or `None` if the fragment does not have it
+    pub fn get_copy<T: ComponentValue + Copy>(&self, component: Component<T>) -> Option<T> {
+        self.world()
+            .get(self.fragment.id, component)
+            .ok()
+            .map(|v| *v)
     }
 
     /// Sets a component value
     pub fn set<T: ComponentValue>(&mut self, component: Component<T>, value: T) -> &mut Self {
-        self.world.set(self.fragment.id, component, value).unwrap();
+        let id = self.fragment.id;
+        self.world_mut().set(id, component, value).unwrap();
         self
     }
 
-    pub fn on_event<T: ComponentValue, F: 'static + FnMut(Entity, &World, &T) + Send + Sync>(
+    /// Sets a relation from this fragment to `target`, such as `child_of(target)`
+    pub fn set_relation<T: ComponentValue>(
         &mut self,
-        event: Component<EventHook<T>>,
-        mut handler: F,
+        relation: impl Fn(Entity) -> Component<T>,
+        target: Entity,
+        value: T,
     ) -> &mut Self {
-        self.set(event, Box::new(handler))
+        self.set(relation(target), value)
+    }
+
+    /// Adds the tag if absent and removes it if present.
+    ///
+    /// Returns true if the tag is now present.
+    pub fn toggle(&mut self, component: Component<()>) -> bool {
+        let id = self.fragment.id;
+        if self.world().has(id, component) {
+            self.world_mut().remove(id, component).unwrap();
+            false
+        } else {
+            self.world_mut().set(id, component, ()).unwrap();
+            true
+        }
     }
 
-    fn clear(&mut self) -> &mut Self {
-        self.world.despawn_children(self.fragment.id, child_of).ok();
-        self.world
-            .entity_mut(self.fragment.id)
+    /// Flips a boolean component, treating a missing value as false.
+    ///
+    /// Returns the new value.
+    pub fn toggle_bool(&mut self, component: Component<bool>) -> bool {
+        let value = !self.get_copy(component).unwrap_or_default();
+        self.set(component, value);
+        value
+    }
+
+    /// Returns a mutable reference to the component value, initializing it with `init` if absent
+    pub fn get_or_insert_with<T: ComponentValue>(
+        &mut self,
+        component: Component<T>,
+        init: impl FnOnce() -> T,
+    ) -> impl DerefMut<Target = T> + '_ {
+        let id = self.fragment.id;
+        self.world_mut()
+            .entry(id, component)
             .unwrap()
-            .retain(|k| k == widget().key());
+            .or_insert_with(init)
+    }
+
+    /// Saves the component's value with the app state. See [`crate::app::save_state`]
+    pub fn persist<T>(&mut self, component: Component<T>) -> &mut Self
+    where
+        T: ComponentValue + Serialize + DeserializeOwned,
+    {
+        self.get_or_insert_with(persistent(), Vec::new)
+            .push(Persisted::new(component));
+        self
+    }
+
+    /// Removes a component value, if present
+    pub fn remove<T: ComponentValue>(&mut self, component: Component<T>) -> &mut Self {
+        let id = self.fragment.id;
+        self.world_mut().remove(id, component).ok();
+        self
+    }
+
+    /// Adds a hook for an event, after any hooks already registered for it.
+    ///
+    /// The handler is usually a closure, which may return a
+    /// [`Propagation`](crate::events::Propagation) to consume bubbling events, or nothing to let
+    /// them continue. The references passed to a closure need their types spelled out, such as
+    /// `|id, world: &World, key: &KeyEvent|`. See [`EventHandler`] for the other handlers.
+    pub fn on_event<T: ComponentValue>(
+        &mut self,
+        event: Component<EventHooks<T>>,
+        handler: impl EventHandler<T>,
+    ) -> &mut Self {
+        self.get_or_insert_with(event, Vec::new)
+            .push(Box::new(handler));
+        self
+    }
+
+    /// Removes every hook registered for an event
+    pub fn clear_hooks<T: ComponentValue>(&mut self, event: Component<EventHooks<T>>) -> &mut Self {
+        self.remove(event)
+    }
+
+    /// Sets the component each time `signal` yields a value, starting with its current one.
+    ///
+    /// The values are applied through the event loop. Like [`Fragment::spawn_task`], the binding
+    /// lives as long as the fragment, and stops when it is despawned or cleared.
+    ///
+    /// # Panics
+    ///
+    /// If called outside of a Tokio runtime, as the binding is driven by a spawned task.
+    pub fn bind<T, S>(&mut self, component: Component<T>, signal: S) -> &mut Self
+    where
+        T: ComponentValue,
+        S: 'static + Signal<Item = T> + Send,
+    {
+        let fragment = self.fragment.downgrade();
+        let token = CancellationToken::new();
+        let handle = tokio::spawn({
+            let token = token.clone();
+            async move {
+                let mut values = std::pin::pin!(signal.to_stream());
+                loop {
+                    let value = tokio::select! {
+                        _ = token.cancelled() => break,
+                        value = values.next() => value,
+                    };
+
+                    // Stop once the signal ends or the app is gone
+                    let Some(value) = value else { break };
+                    if !fragment.upgrade_set(component, value) {
+                        break;
+                    }
+                }
+            }
+        });
+
+        self.get_or_insert_with(tasks(), Vec::new)
+            .push(FragmentTask {
+                token,
+                handle: Some(handle),
+            });
+        self
+    }
+
+    /// Despawns all descendants and removes every component, except the [`widget`] tag and those
+    /// set from the outside: its parent, [`order`], [`position`] and [`constraints`], its
+    /// [`name`], and the [`viewport`] and [`headless`] of the root. Callbacks registered through
+    /// [`AppRef::on_despawn`] for the fragment itself are kept, as it is not despawned.
+    ///
+    /// Use this to start over before rendering something else into the fragment.
+    pub fn clear(&mut self) -> &mut Self {
+        let id = self.fragment.id;
+        let world = self.world.as_mut().unwrap();
+        for child in children_of(world, id) {
+            take_despawn_callbacks(world, child, &mut self.despawned);
+            self.fragment.app.lifecycle.unmount(world, child);
+        }
+        world.despawn_children(id, child_of).ok();
+
+        self.clear_components()
+    }
+
+    /// Removes every component like [`Self::clear`], but keeps the children.
+    ///
+    /// This resets the state of the fragment itself, such as its
+    /// [`content`](crate::components::content), without tearing down its subtree.
+    ///
+    /// The components set from the outside are kept here as well, including [`position`]. The
+    /// parent's layout only writes those when its own children change, so removing them would
+    /// leave the fragment unplaced until something else relays the parent.
+    pub fn clear_components(&mut self) -> &mut Self {
+        let id = self.fragment.id;
+        let parent = parent_of(self.world(), id).map(|parent| child_of(parent).key());
+
+        // flax panics if `retain` keeps every component, which makes sure at least one is removed
+        let mut entity = self.world_mut().entity_mut(id).unwrap();
+        entity.set(clearing(), ()).unwrap();
+        // The fragment itself lives on, so its despawn callbacks are kept for when it is despawned
+        entity.retain(|k| {
+            k == widget().key()
+                || k == despawn_callbacks().key()
+                || Some(k) == parent
+                || is_placement(k)
+        });
 
         self
     }
 }
+
+impl Drop for FragmentRef<'_> {
+    fn drop(&mut self) {
+        // The callbacks may lock the world themselves
+        self.world.take();
+        for (id, callback) in self.despawned.drain(..) {
+            callback(id);
+        }
+    }
+}
+
+/// Returns true for the components which are set on a fragment from the outside, and therefore
+/// kept when the fragment is cleared: its [`order`], the [`position`] and [`constraints`] given by
+/// its parent's layout, its [`name`], and the [`viewport`] and [`headless`] of the root.
+fn is_placement(key: ComponentKey) -> bool {
+    [
+        order().key(),
+        position().key(),
+        constraints().key(),
+        name().key(),
+        viewport().key(),
+        headless().key(),
+    ]
+    .contains(&key)
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use futures::FutureExt;
+
+    use crate::{
+        app::App,
+        components::{content, position, selected},
+        widgets::Text,
+    };
+
+    use super::*;
+
+    struct Pending;
+
+    #[async_trait]
+    impl Widget for Pending {
+        type Output = ();
+
+        async fn mount(self, _: Fragment) {
+            futures::future::pending().await
+        }
+    }
+
+    struct Cleanup(tokio::sync::oneshot::Sender<()>);
+
+    #[async_trait]
+    impl Widget for Cleanup {
+        type Output = ();
+
+        async fn mount(self, mut fragment: Fragment) {
+            let done = self.0;
+            fragment.spawn_task(|token| async move {
+                token.cancelled().await;
+                // Flush or close resources
+                tokio::task::yield_now().await;
+                done.send(()).unwrap();
+            });
+
+            futures::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn task_cancelled_on_despawn() {
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let mut child = root.attach(Cleanup(tx));
+        let id = child.id();
+        assert!((&mut child).now_or_never().is_none());
+        assert!(handle.world().has(id, tasks()));
+
+        drop(child);
+
+        tokio::time::timeout(TASK_GRACE_PERIOD, rx)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(!handle.world().is_alive(id));
+    }
+
+    #[tokio::test]
+    async fn bound_component_follows_signal() {
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+
+        // Closed once the binding drops the signal
+        let (alive, dropped) = tokio::sync::oneshot::channel::<()>();
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let text = futures_signals::signal::from_stream(rx).map(move |v: Option<String>| {
+            let _alive = &alive;
+            v.unwrap_or_default()
+        });
+
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let mut values = root.signal(content()).to_stream();
+        root.write().bind(content(), text);
+
+        assert_eq!(values.next().await.as_deref(), Some(""));
+
+        tx.unbounded_send("Hello".to_string()).unwrap();
+        assert_eq!(values.next().await.as_deref(), Some("Hello"));
+
+        // The binding stops with the fragment, dropping the signal
+        let id = root.id();
+        handle.enqueue(Event::Despawn(id)).unwrap();
+        assert_eq!(values.next().await, None);
+        tokio::time::timeout(TASK_GRACE_PERIOD, dropped)
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(!handle.world().is_alive(id));
+    }
+
+    #[test]
+    fn name_path_joins_ancestors() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut world = handle.world();
+        let root = Fragment::spawn(&mut world, handle.clone(), None);
+        let row = Fragment::spawn(&mut world, handle.clone(), Some(root.id()));
+        let clock = Fragment::spawn(&mut world, handle.clone(), Some(row.id()));
+        drop(world);
+
+        assert_eq!(clock.name_path(), "0.0");
+
+        let mut world = handle.world();
+        for (fragment, label) in [(&root, "Application"), (&row, "Row"), (&clock, "Clock")] {
+            world.set(fragment.id(), name(), label.into()).unwrap();
+        }
+        drop(world);
+
+        assert_eq!(clock.name_path(), "Application.Row.Clock");
+    }
+
+    #[test]
+    fn signal_yields_changes() {
+        use futures::StreamExt;
+        use futures_signals::signal::SignalExt;
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        root.write().set(content(), "a".into());
+
+        let mut values = root.signal(content()).to_stream();
+        assert_eq!(values.next().now_or_never(), Some(Some("a".into())));
+        assert_eq!(values.next().now_or_never(), None);
+
+        root.write().set(content(), "b".into());
+        assert_eq!(values.next().now_or_never(), Some(Some("b".into())));
+
+        root.write().set(content(), "c".into());
+        assert_eq!(values.next().now_or_never(), Some(Some("c".into())));
+        assert_eq!(values.next().now_or_never(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn put_with_deadline_falls_back() {
+        struct Hang;
+
+        #[async_trait]
+        impl Widget for Hang {
+            type Output = &'static str;
+
+            async fn mount(self, mut fragment: Fragment) -> &'static str {
+                fragment.write().set(content(), "Loading".into());
+                fragment.attach(Pending).await;
+                "Loaded"
+            }
+        }
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let output = root
+            .put_with_deadline(Hang, Duration::from_secs(1), || "Timed out")
+            .await;
+
+        assert_eq!(output, "Timed out");
+        assert!(root.children().is_empty());
+        assert!(!handle.world().has(root.id(), content()));
+    }
+
+    struct Labelled(&'static str);
+
+    #[async_trait]
+    impl Widget for Labelled {
+        type Output = ();
+
+        async fn mount(self, mut fragment: Fragment) {
+            fragment.write().set(selected(), ());
+            fragment.attach(Text::new(self.0)).await
+        }
+    }
+
+    #[test]
+    fn put_replaces_previous_widget() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let widgets = || {
+            Query::new(entity_ids())
+                .with(widget())
+                .borrow(&handle.world())
+                .count()
+        };
+
+        for text in ["a", "b", "c"] {
+            root.put(Labelled(text)).now_or_never().unwrap();
+
+            let children = root.children();
+            assert_eq!(children.len(), 1);
+            assert_eq!(
+                handle.world().get(children[0], content()).as_deref(),
+                Ok(&text.into())
+            );
+            assert_eq!(widgets(), 2);
+        }
+
+        root.put(Text::new("plain")).now_or_never().unwrap();
+        assert!(root.children().is_empty());
+        assert!(!handle.world().has(root.id(), selected()));
+    }
+
+    #[test]
+    fn put_runs_despawn_callbacks() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        root.put(Labelled("a")).now_or_never().unwrap();
+        let child = root.children()[0];
+        let grandchild = Entity::builder()
+            .tag(child_of(child))
+            .spawn(&mut handle.world());
+
+        let despawned = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        for id in [child, grandchild] {
+            let despawned = despawned.clone();
+            handle
+                .on_despawn(id, move |id| despawned.lock().unwrap().push(id))
+                .unwrap();
+        }
+
+        root.put(Text::new("plain")).now_or_never().unwrap();
+        assert_eq!(*despawned.lock().unwrap(), [grandchild, child]);
+    }
+
+    #[test]
+    fn put_keeps_own_despawn_callbacks() {
+        let (handle, events) = App::new().start();
+        let mut events = Box::pin(events);
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let despawned = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        {
+            let despawned = despawned.clone();
+            handle
+                .on_despawn(root.id(), move |id| despawned.lock().unwrap().push(id))
+                .unwrap();
+        }
+
+        root.put(Text::new("a")).now_or_never().unwrap();
+        root.put(Text::new("b")).now_or_never().unwrap();
+        assert!(despawned.lock().unwrap().is_empty());
+
+        let id = root.id();
+        handle.enqueue(Event::Despawn(id)).unwrap();
+        assert!(events.as_mut().now_or_never().is_none());
+        assert_eq!(*despawned.lock().unwrap(), [id]);
+    }
+
+    #[tokio::test]
+    async fn put_keeps_layout_position() {
+        use futures::StreamExt;
+        use futures_signals::signal::Mutable;
+
+        use crate::widgets::Row;
+
+        /// Puts each text it is given
+        struct Swap(Mutable<&'static str>);
+
+        #[async_trait]
+        impl Widget for Swap {
+            type Output = ();
+
+            async fn mount(self, mut fragment: Fragment) {
+                let mut texts = self.0.signal().to_stream();
+                while let Some(text) = texts.next().await {
+                    fragment.put(Text::new(text)).await;
+                }
+            }
+        }
+
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+
+        let text = Mutable::new("xy");
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let row = root.attach(Row::new((Text::new("ab"), Swap(text.clone()))));
+        let row_id = row.id();
+        tokio::spawn(row);
+        tokio::task::yield_now().await;
+
+        let swap = children_of(&handle.world(), row_id)[1];
+        let placed = || handle.world().get(swap, position()).ok().map(|v| *v);
+        assert_eq!(placed(), Some(Vec2::new(2.0, 0.0)));
+
+        // The same size, so the row does not lay out again
+        text.set("zw");
+        tokio::task::yield_now().await;
+        assert_eq!(
+            handle.world().get(swap, content()).as_deref(),
+            Ok(&"zw".into())
+        );
+        assert_eq!(placed(), Some(Vec2::new(2.0, 0.0)));
+    }
+
+    #[test]
+    fn put_keyed_remounts_on_new_key() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let text = |id| handle.world().get(id, content()).unwrap().clone();
+
+        assert_eq!(
+            root.put_keyed(1, Labelled("a")).now_or_never(),
+            Some(Some(()))
+        );
+        let first = root.children()[0];
+
+        // Same key, the widget is not mounted
+        assert_eq!(root.put_keyed(1, Labelled("b")).now_or_never(), Some(None));
+        assert_eq!(root.children(), [first]);
+        assert_eq!(text(first), "a");
+
+        assert_eq!(
+            root.put_keyed(2, Labelled("c")).now_or_never(),
+            Some(Some(()))
+        );
+        let children = root.children();
+        assert_eq!(children.len(), 1);
+        assert!(!handle.world().is_alive(first));
+        assert_eq!(text(children[0]), "c");
+
+        // The same value of another type is another key
+        assert_eq!(
+            root.put_keyed(2u8, Labelled("d")).now_or_never(),
+            Some(Some(()))
+        );
+        assert_eq!(text(root.children()[0]), "d");
+    }
+
+    #[test]
+    fn detach_removes_single_child() {
+        let (handle, events) = App::new().start();
+        let mut events = Box::pin(events);
+
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        for text in ["a", "b", "c"] {
+            root.attach(Text::new(text)).now_or_never().unwrap();
+        }
+
+        let children = root.children();
+        root.detach(children[1]).unwrap();
+        assert!(events.as_mut().now_or_never().is_none());
+
+        assert!(!handle.world().is_alive(children[1]));
+        assert_eq!(root.children(), [children[0], children[2]]);
+
+        assert!(matches!(
+            root.detach(children[1]),
+            Err(Error::NotAChild { .. })
+        ));
+        assert!(root.detach(root.id()).is_err());
+    }
+
+    #[test]
+    fn clear_components_keeps_children() {
+        let app = App::new();
+        let handle = app.handle();
+        let root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let mut row = Fragment::spawn(&mut handle.world(), handle.clone(), Some(root.id()));
+
+        row.attach(Text::new("a")).now_or_never().unwrap();
+        row.write()
+            .set(content(), "Row".into())
+            .set(position(), Vec2::ONE);
+
+        row.write().clear_components();
+        let children = row.children();
+        assert_eq!(children.len(), 1);
+        {
+            let world = handle.world();
+            assert!(!world.has(row.id(), content()));
+            // Set by the parent's layout rather than the widget
+            assert_eq!(world.get(row.id(), position()).as_deref(), Ok(&Vec2::ONE));
+            assert!(world.has(row.id(), widget()));
+            assert_eq!(parent_of(&world, row.id()), Some(root.id()));
+        }
+
+        row.write().clear();
+        assert!(row.children().is_empty());
+        assert!(!handle.world().is_alive(children[0]));
+    }
+
+    #[tokio::test]
+    async fn put_blocking_constructs_off_task() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        root.put_blocking(move || {
+            tx.send(std::thread::current().id()).unwrap();
+            Text::new((0..3).map(|i| i.to_string()).collect::<String>())
+        })
+        .await;
+
+        assert_ne!(rx.recv().unwrap(), std::thread::current().id());
+        assert_eq!(*handle.world().get(root.id(), content()).unwrap(), "012");
+    }
+
+    #[test]
+    fn get_or_insert_with() {
+        component! {
+            counter: u32,
+        }
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let mut initialized = 0;
+        for _ in 0..3 {
+            let mut frag = root.write();
+            let mut counter = frag.get_or_insert_with(counter(), || {
+                initialized += 1;
+                0
+            });
+            *counter += 1;
+        }
+
+        assert_eq!(initialized, 1);
+        assert_eq!(*handle.world().get(root.id(), counter()).unwrap(), 3);
+    }
+
+    #[test]
+    fn parent_of_attached_chain() {
+        struct Chain {
+            depth: usize,
+            parents: std::sync::mpsc::Sender<(Entity, Option<Entity>)>,
+        }
+
+        #[async_trait]
+        impl Widget for Chain {
+            type Output = ();
+
+            async fn mount(self, mut fragment: Fragment) {
+                self.parents
+                    .send((fragment.id(), fragment.parent()))
+                    .unwrap();
+
+                if self.depth > 0 {
+                    fragment
+                        .attach(Chain {
+                            depth: self.depth - 1,
+                            parents: self.parents,
+                        })
+                        .await
+                }
+            }
+        }
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        assert_eq!(root.parent(), None);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        root.attach(Chain {
+            depth: 2,
+            parents: tx,
+        })
+        .now_or_never()
+        .unwrap();
+
+        let mut expected = root.id();
+        for (id, parent) in rx.try_iter() {
+            assert_eq!(parent, Some(expected));
+            expected = id;
+        }
+        assert_eq!(depth_of(&handle.world(), expected), 3);
+    }
+
+    #[test]
+    fn get_reads_back() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let mut frag = root.write();
+        assert_eq!(frag.get(content()), None);
+        assert_eq!(frag.get_copy(position()), None);
+
+        frag.set(content(), "Hello".into())
+            .set(position(), Vec2::ONE);
+        assert_eq!(frag.get(content()).as_deref(), Some("Hello"));
+        assert_eq!(frag.get_copy(position()), Some(Vec2::ONE));
+    }
+
+    #[test]
+    fn toggle() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        assert!(root.write().toggle(selected()));
+        assert!(handle.world().has(root.id(), selected()));
+        assert!(!root.write().toggle(selected()));
+        assert!(!handle.world().has(root.id(), selected()));
+    }
+
+    #[test]
+    fn attach_at_front() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let a = root.attach(Pending);
+        let b = root.attach(Pending);
+        let c = root.attach_at(0, Pending);
+
+        assert_eq!(root.children(), [c.id(), a.id(), b.id()]);
+    }
+
+    #[test]
+    fn set_relation() {
+        component! {
+            follows(target): f32,
+        }
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut a = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let b = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        a.write().set_relation(follows, b.id(), 0.5);
+
+        let world = handle.world();
+        assert_eq!(world.get(a.id(), follows(b.id())).as_deref(), Ok(&0.5));
+        assert!(!world.has(b.id(), follows(a.id())));
+    }
+
+    #[test]
+    fn hooks_accumulate() {
+        component! {
+            on_click: EventHooks<()>,
+        }
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut button = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let clicks = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        for name in ["first", "second"] {
+            let clicks = clicks.clone();
+            button
+                .write()
+                .on_event(on_click(), move |_, _: &World, _: &()| {
+                    clicks.lock().unwrap().push(name);
+                });
+        }
+
+        crate::events::send_event(&handle.world(), on_click(), ());
+        assert_eq!(*clicks.lock().unwrap(), ["first", "second"]);
+
+        button.write().clear_hooks(on_click());
+        crate::events::send_event(&handle.world(), on_click(), ());
+        assert_eq!(clicks.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn weak_fragment_in_hook() {
+        component! {
+            on_click: EventHooks<()>,
+        }
+
+        let (handle, events) = App::new().start();
+        let mut events = Box::pin(events);
+
+        let target = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let mut button = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let weak = target.downgrade();
+        button
+            .write()
+            .on_event(on_click(), move |_, _: &World, _: &()| {
+                assert!(weak.upgrade_set(content(), "Clicked".into()));
+            });
+
+        crate::events::send_event(&handle.world(), on_click(), ());
+        assert!(events.as_mut().now_or_never().is_none());
+
+        let world = handle.world();
+        assert_eq!(
+            world.get(target.id(), content()).as_deref(),
+            Ok(&"Clicked".into())
+        );
+        assert!(!world.has(button.id(), content()));
+    }
+
+    #[test]
+    fn props_handle_updates_in_place() {
+        struct Greeting {
+            renders: usize,
+        }
+
+        impl StatefulWidget for Greeting {
+            type Props = &'static str;
+
+            fn update(&mut self, fragment: &mut FragmentRef, name: &'static str) {
+                self.renders += 1;
+                fragment.set(content(), format!("Hello, {name} #{}", self.renders));
+            }
+        }
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let mut greeting = root.mount_stateful_handle(Greeting { renders: 0 }, "World");
+        let id = greeting.id();
+        assert_eq!(
+            *handle.world().get(id, content()).unwrap(),
+            "Hello, World #1"
+        );
+
+        greeting.update("Fragment");
+        assert_eq!(root.children(), [id]);
+        assert_eq!(
+            *handle.world().get(id, content()).unwrap(),
+            "Hello, Fragment #2"
+        );
+    }
+
+    #[test]
+    fn max_depth_stops_recursion() {
+        struct Recursive;
+
+        #[async_trait]
+        impl Widget for Recursive {
+            type Output = ();
+
+            async fn mount(self, mut fragment: Fragment) {
+                fragment.attach(Recursive).await
+            }
+        }
+
+        let (handle, events) = App::new().with_max_depth(3).start();
+        let mut events = Box::pin(events);
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let mut put = Box::pin(root.put(Recursive));
+        assert!(put.as_mut().now_or_never().is_none());
+
+        let depths = || {
+            let world = handle.world();
+            let depths = Query::new(entity_ids())
+                .with(widget())
+                .borrow(&world)
+                .iter()
+                .map(|id| depth_of(&world, id))
+                .sorted()
+                .collect_vec();
+            depths
+        };
+
+        // The fragment past the limit is spawned, but nothing is mounted into it, and it is
+        // despawned by the event loop
+        assert_eq!(depths(), [0, 1, 2, 3, 4]);
+        assert!(events.as_mut().now_or_never().is_none());
+        assert_eq!(depths(), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn try_attach_past_max_depth() {
+        /// Returns the depth at which it could not nest further
+        struct Recursive;
+
+        #[async_trait]
+        impl Widget for Recursive {
+            type Output = usize;
+
+            async fn mount(self, mut fragment: Fragment) -> usize {
+                match fragment.try_attach(Recursive) {
+                    Ok(child) => child.await,
+                    Err(Error::MaxDepthExceeded { max_depth, .. }) => {
+                        assert_eq!(max_depth, 3);
+                        depth_of(&fragment.app().world(), fragment.id())
+                    }
+                    Err(err) => panic!("{err}"),
+                }
+            }
+        }
+
+        let app = App::new().with_max_depth(3);
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        assert_eq!(root.put(Recursive).now_or_never(), Some(3));
+        assert_eq!(
+            Query::new(entity_ids())
+                .with(widget())
+                .borrow(&handle.world())
+                .count(),
+            4
+        );
+    }
+
+    #[test]
+    fn put_sequence_replaces_content() {
+        let app = App::new();
+        let handle = app.handle();
+        let root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let mut frag = Fragment::spawn(&mut handle.world(), handle.clone(), Some(root.id()));
+
+        struct First;
+
+        #[async_trait]
+        impl Widget for First {
+            type Output = ();
+
+            async fn mount(self, mut fragment: Fragment) {
+                fragment
+                    .write()
+                    .set(content(), "First".into())
+                    .set(selected(), ());
+            }
+        }
+
+        frag.put_sequence(vec![Box::new(First), Box::new(Text::new("Second"))])
+            .now_or_never()
+            .unwrap();
+
+        assert_eq!(root.children(), [frag.id()]);
+
+        let world = handle.world();
+        assert_eq!(
+            world.get(frag.id(), content()).as_deref(),
+            Ok(&"Second".into())
+        );
+        assert!(!world.has(frag.id(), selected()));
+    }
+}
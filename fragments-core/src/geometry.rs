@@ -0,0 +1,109 @@
+//! Screen space regions and hit testing.
+use flax::{entity_ids, Entity, Query, World};
+use glam::Vec2;
+
+use crate::{
+    components::{hit_rect, size, widget},
+    fragment::{is_hidden, parent_of},
+    layout::absolute_position,
+};
+
+/// An axis aligned rectangle
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rect {
+    pub pos: Vec2,
+    pub size: Vec2,
+}
+
+impl Rect {
+    pub fn new(pos: Vec2, size: Vec2) -> Self {
+        Self { pos, size }
+    }
+
+    /// Returns true if `point` lies within the rectangle. The far edges are exclusive, so that
+    /// adjacent rectangles do not overlap.
+    pub fn contains(&self, point: Vec2) -> bool {
+        let max = self.pos + self.size;
+        point.cmpge(self.pos).all() && point.cmplt(max).all()
+    }
+
+    /// Returns the smallest rectangle containing both rectangles
+    pub fn union(self, other: Self) -> Self {
+        let min = self.pos.min(other.pos);
+        let max = (self.pos + self.size).max(other.pos + other.size);
+        Self::new(min, max - min)
+    }
+
+    /// Moves the rectangle by `offset`
+    pub fn translate(self, offset: Vec2) -> Self {
+        Self {
+            pos: self.pos + offset,
+            ..self
+        }
+    }
+}
+
+/// Returns the screen space region of `id` which responds to the pointer.
+///
+/// This is the [`hit_rect`] if present, and otherwise the widget's [`size`] at its position.
+pub fn hit_region(world: &World, id: Entity) -> Option<Rect> {
+    let local = match world.get(id, hit_rect()) {
+        Ok(rect) => *rect,
+        Err(_) => Rect::new(Vec2::ZERO, *world.get(id, size()).ok()?),
+    };
+
+    Some(local.translate(absolute_position(world, id)))
+}
+
+/// Returns the widget under `point`.
+///
+/// When hit regions overlap, the most deeply nested widget wins, as it is drawn on top of its
+/// ancestors. [`hidden`](crate::components::hidden) widgets are never hit.
+pub fn hit_test(world: &World, point: Vec2) -> Option<Entity> {
+    let depth = |mut id| {
+        let mut depth = 0;
+        while let Some(parent) = parent_of(world, id) {
+            depth += 1;
+            id = parent;
+        }
+        depth
+    };
+
+    Query::new(entity_ids())
+        .with(widget())
+        .borrow(world)
+        .iter()
+        .filter(|&id| !is_hidden(world, id))
+        .filter(|&id| hit_region(world, id).is_some_and(|rect| rect.contains(point)))
+        .max_by_key(|&id| depth(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use crate::{app::App, components::position, Fragment};
+
+    use super::*;
+
+    #[test]
+    fn hit_rect_extends_clickable_area() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        root.write().set(size(), vec2(10.0, 10.0));
+
+        let mut icon = Fragment::spawn(&mut handle.world(), handle.clone(), Some(root.id()));
+        icon.write()
+            .set(position(), vec2(4.0, 4.0))
+            .set(size(), vec2(1.0, 1.0))
+            .set(hit_rect(), Rect::new(vec2(-1.0, -1.0), vec2(3.0, 3.0)));
+
+        let world = handle.world();
+        assert_eq!(hit_test(&world, vec2(4.0, 4.0)), Some(icon.id()));
+        // Outside of the drawn cell, but within the padded hit region
+        assert_eq!(hit_test(&world, vec2(5.0, 3.0)), Some(icon.id()));
+        assert_eq!(hit_test(&world, vec2(6.0, 4.0)), Some(root.id()));
+        assert_eq!(hit_test(&world, vec2(10.0, 0.0)), None);
+    }
+}
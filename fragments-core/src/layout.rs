@@ -0,0 +1,320 @@
+//! Sizing of widgets relative to their surroundings.
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use flax::{
+    entity_ids, events::ChangeSubscriber, Component, ComponentValue, Entity, FetchExt, Query, World,
+};
+use glam::Vec2;
+use tokio::sync::Notify;
+
+use crate::{
+    components::{
+        direction, measure as measure_component, position, relative_size, size, viewport,
+    },
+    fragment::parent_of,
+    Fragment, Widget,
+};
+
+/// The direction in which content flows
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Left to right
+    #[default]
+    Ltr,
+    /// Right to left
+    Rtl,
+}
+
+/// The axis along which a layout places its children
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    /// Side by side, in the inherited [`Direction`]
+    #[default]
+    Horizontal,
+    /// Top to bottom
+    Vertical,
+}
+
+impl Axis {
+    /// Returns the component of `v` along the axis
+    pub fn along(self, v: Vec2) -> f32 {
+        match self {
+            Axis::Horizontal => v.x,
+            Axis::Vertical => v.y,
+        }
+    }
+
+    /// Returns the component of `v` across the axis
+    pub fn across(self, v: Vec2) -> f32 {
+        match self {
+            Axis::Horizontal => v.y,
+            Axis::Vertical => v.x,
+        }
+    }
+
+    /// Creates a vector from its components along and across the axis
+    pub fn vec(self, along: f32, across: f32) -> Vec2 {
+        match self {
+            Axis::Horizontal => Vec2::new(along, across),
+            Axis::Vertical => Vec2::new(across, along),
+        }
+    }
+}
+
+/// Returns the value of `component` on the closest ancestor which has it, including `id` itself.
+///
+/// This allows a component to apply to a whole subtree.
+pub fn inherited<T: ComponentValue + Clone>(
+    world: &World,
+    id: Entity,
+    component: Component<T>,
+) -> Option<T> {
+    let mut current = Some(id);
+    while let Some(id) = current {
+        if let Ok(value) = world.get(id, component) {
+            return Some(value.clone());
+        }
+
+        current = parent_of(world, id);
+    }
+
+    None
+}
+
+/// Returns the viewport of the closest ancestor, including `id` itself
+pub fn viewport_of(world: &World, id: Entity) -> Option<Vec2> {
+    inherited(world, id, viewport())
+}
+
+/// Returns the inherited layout direction of `id`
+pub fn direction_of(world: &World, id: Entity) -> Direction {
+    inherited(world, id, direction()).unwrap_or_default()
+}
+
+/// Hashes the inputs of a layout pass: the measured children in order, and the container's own
+/// settings.
+///
+/// Layout widgets store the hash in [`layout_hash`](crate::components::layout_hash) and skip passes whose hash is unchanged.
+pub fn hash_layout(children: &[(Entity, Vec2)], settings: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for (id, size) in children {
+        id.hash(&mut hasher);
+        size.to_array().map(f32::to_bits).hash(&mut hasher);
+    }
+
+    settings.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Returns the screen space position of `id`.
+///
+/// A [`position`] is relative to the parent, so the positions of all ancestors are added up.
+/// Fragments without a position are placed at their parent's origin.
+pub fn absolute_position(world: &World, id: Entity) -> Vec2 {
+    let mut pos = Vec2::ZERO;
+    let mut current = Some(id);
+    while let Some(id) = current {
+        if let Ok(local) = world.get(id, position()) {
+            pos += *local;
+        }
+
+        current = parent_of(world, id);
+    }
+
+    pos
+}
+
+/// The range of sizes a container allows a child to take.
+///
+/// This is how space is passed down the tree, such as for a widget which fills its parent. A
+/// container sets the [`constraints`](crate::components::constraints) of each child before laying
+/// it out, and widgets which size themselves to the space they are given read them in `mount`, and
+/// update their [`size`] when they change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Constraints {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Constraints {
+    pub fn new(min: Vec2, max: Vec2) -> Self {
+        Self { min, max }
+    }
+
+    /// Allows any size
+    pub fn unbounded() -> Self {
+        Self::new(Vec2::ZERO, Vec2::splat(f32::INFINITY))
+    }
+
+    /// Returns the size closest to `size` which satisfies the constraints
+    pub fn clamp(&self, size: Vec2) -> Vec2 {
+        size.max(self.min).min(self.max)
+    }
+
+    /// Returns the largest allowed size. Unbounded axes fall back to the minimum.
+    pub fn fill(&self) -> Vec2 {
+        let fill = |max: f32, min| if max.is_finite() { max } else { min };
+        Vec2::new(fill(self.max.x, self.min.x), fill(self.max.y, self.min.y))
+    }
+}
+
+impl Default for Constraints {
+    fn default() -> Self {
+        Self::unbounded()
+    }
+}
+
+/// Computes the intrinsic size of a widget under constraints, such as the height of wrapped text
+/// for a given width.
+pub trait Measure: Send + Sync {
+    /// Returns the size the widget wants when `available` space is offered
+    fn measure(&self, available: Vec2) -> Vec2;
+}
+
+impl<F> Measure for F
+where
+    F: Fn(Vec2) -> Vec2 + Send + Sync,
+{
+    fn measure(&self, available: Vec2) -> Vec2 {
+        (self)(available)
+    }
+}
+
+/// Returns the size `id` wants when `available` space is offered.
+///
+/// Widgets without a [`Measure`] always want their current [`size`]. Returns `None` if the
+/// widget has neither.
+pub fn measure(world: &World, id: Entity, available: Vec2) -> Option<Vec2> {
+    match world.get(id, measure_component()) {
+        Ok(measure) => Some(measure.measure(available)),
+        Err(_) => world.get(id, size()).ok().map(|size| *size),
+    }
+}
+
+/// Sets the [`size`] of every entity with a [`relative_size`] from its viewport.
+///
+/// The size is only written when it differs, to not wake layout widgets needlessly.
+pub fn resolve_relative_sizes(world: &mut World) {
+    let resolved = Query::new((entity_ids(), relative_size(), size().opt()))
+        .borrow(world)
+        .iter()
+        .filter_map(|(id, &relative, current)| {
+            let resolved = viewport_of(world, id)? * relative;
+            (current != Some(&resolved)).then_some((id, resolved))
+        })
+        .collect::<Vec<_>>();
+
+    for (id, resolved) in resolved {
+        world.set(id, size(), resolved).unwrap();
+    }
+}
+
+/// Keeps the size of widgets with a [`relative_size`] up to date as the viewport changes.
+///
+/// Resizing cascades down the tree as layout widgets react to the new sizes of their children.
+pub struct RelativeLayout;
+
+#[async_trait]
+impl Widget for RelativeLayout {
+    type Output = ();
+
+    async fn mount(self, mut fragment: Fragment) {
+        let changed = Arc::new(Notify::new());
+        fragment
+            .write()
+            .world_mut()
+            .subscribe(ChangeSubscriber::new(
+                &[viewport().key(), relative_size().key()],
+                Arc::downgrade(&changed),
+            ));
+
+        loop {
+            resolve_relative_sizes(fragment.write().world_mut());
+            changed.notified().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::Event;
+    use futures::stream;
+    use glam::vec2;
+
+    use crate::{
+        app::App,
+        canvas::{draw_widgets, Canvas},
+        fragment::children_of,
+        terminal::InputLayer,
+        widgets::{Row, Text},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn nested_row_absolute_position() {
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+
+        let root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let mut stack = Fragment::spawn(&mut handle.world(), handle.clone(), Some(root.id()));
+        stack.write().set(position(), vec2(3.0, 2.0));
+
+        let row = stack.attach(Row::new((Text::new("ab"), Text::new("cd"))).with_padding(1.0));
+        let row_id = row.id();
+        handle
+            .world()
+            .set(row_id, position(), vec2(1.0, 1.0))
+            .unwrap();
+        tokio::spawn(row);
+        tokio::task::yield_now().await;
+
+        let leaf = children_of(&handle.world(), row_id)[1];
+        assert_eq!(stack.absolute_position(leaf), vec2(7.0, 3.0));
+
+        let mut canvas = Canvas::new(glam::uvec2(10, 4));
+        draw_widgets(&handle.world(), &mut canvas);
+        assert_eq!(canvas.to_string().lines().nth(3), Some("    ab cd "));
+    }
+
+    #[tokio::test]
+    async fn resize_updates_relative_size() {
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        root.write().set(viewport(), vec2(80.0, 24.0));
+
+        tokio::spawn(root.attach(RelativeLayout));
+
+        let mut child = Fragment::spawn(&mut handle.world(), handle.clone(), Some(root.id()));
+        child.write().set(relative_size(), vec2(0.5, 1.0));
+
+        tokio::task::yield_now().await;
+        assert_eq!(
+            *handle.world().get(child.id(), size()).unwrap(),
+            vec2(40.0, 24.0)
+        );
+
+        root.attach(InputLayer::from_stream(stream::iter([Ok(Event::Resize(
+            100, 50,
+        ))])))
+        .await
+        .unwrap();
+
+        tokio::task::yield_now().await;
+        assert_eq!(
+            *handle.world().get(root.id(), viewport()).unwrap(),
+            vec2(100.0, 50.0)
+        );
+        assert_eq!(
+            *handle.world().get(child.id(), size()).unwrap(),
+            vec2(50.0, 50.0)
+        );
+    }
+}
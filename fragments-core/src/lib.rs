@@ -1,14 +1,26 @@
 // #![warn(missing_docs)]
 #![deny(rustdoc::broken_intra_doc_links)]
 
+pub mod accessibility;
 pub mod app;
+pub mod batch;
+pub mod canvas;
 pub mod components;
-mod desync;
+pub mod desync;
 pub mod error;
 pub mod events;
 mod fragment;
+pub mod geometry;
+pub mod layout;
+mod lifecycle;
 pub mod notify;
+pub mod reconcile;
+pub mod style;
+pub mod terminal;
+pub mod testing;
+pub mod throttle;
 mod widget;
+pub mod widgets;
 
 pub use fragment::*;
 pub use widget::*;
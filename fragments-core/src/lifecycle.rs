@@ -0,0 +1,70 @@
+//! Bookkeeping of mounted fragments in debug builds, used to find fragments which are never
+//! unmounted.
+use std::collections::BTreeMap;
+
+use flax::{Entity, World};
+use parking_lot::Mutex;
+
+use crate::fragment::{children_of, parent_of};
+
+/// Tracks which fragments are mounted, and whether the widget mounted in each has completed.
+///
+/// Does nothing in release builds.
+#[derive(Debug, Default)]
+pub(crate) struct Lifecycle {
+    mounted: Mutex<BTreeMap<Entity, bool>>,
+}
+
+impl Lifecycle {
+    pub(crate) fn mount(&self, id: Entity) {
+        if cfg!(debug_assertions) {
+            tracing::trace!(?id, "mount");
+            self.mounted.lock().insert(id, false);
+        }
+    }
+
+    /// The widget of `id` completed, handing the fragment over to its parent
+    pub(crate) fn complete(&self, id: Entity) {
+        if let Some(completed) = self.mounted.lock().get_mut(&id) {
+            *completed = true;
+        }
+    }
+
+    /// Unmounts `id` and its descendants, which are about to be despawned
+    pub(crate) fn unmount(&self, world: &World, id: Entity) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        for child in children_of(world, id) {
+            self.unmount(world, child);
+        }
+
+        if self.mounted.lock().remove(&id).is_some() {
+            tracing::trace!(?id, "unmount");
+        }
+    }
+
+    /// Returns the fragments which are mounted, but neither owned by a running widget nor by a
+    /// parent.
+    ///
+    /// Fragments without a parent are roots, which are owned by the app. A fragment remains
+    /// owned by its parent once its widget completes.
+    pub(crate) fn leaked(&self, world: &World) -> Vec<Entity> {
+        let mounted = self.mounted.lock();
+
+        let is_owned = |mut id| loop {
+            match parent_of(world, id) {
+                None => return true,
+                Some(_) if mounted.get(&id) == Some(&false) => return false,
+                Some(parent) => id = parent,
+            }
+        };
+
+        mounted
+            .keys()
+            .copied()
+            .filter(|&id| world.is_alive(id) && !is_owned(id))
+            .collect()
+    }
+}
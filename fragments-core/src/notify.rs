@@ -1,44 +1,99 @@
 use std::{
+    pin::Pin,
     sync::{
         atomic::{
             AtomicBool,
             Ordering::{self, SeqCst},
         },
-        Arc,
+        Arc, Weak,
     },
     task::{Poll, Waker},
 };
 
-use futures::Future;
+use flax::events::{ChangeEvent, EventHandler};
+use futures::{Future, Stream};
 use parking_lot::Mutex;
 
-struct AsyncSignal {
+/// The wake state of a single receiver
+struct Slot {
     waker: Mutex<Option<Waker>>,
     woken: AtomicBool,
 }
 
-impl AsyncSignal {
-    pub fn new() -> Self {
+impl Slot {
+    fn new(woken: bool) -> Self {
         Self {
             waker: Mutex::new(None),
-            woken: AtomicBool::new(false),
+            woken: AtomicBool::new(woken),
         }
     }
 
-    pub fn wake(&self) {
+    fn wake(&self) {
         self.woken.store(true, SeqCst);
         if let Some(waker) = &*self.waker.lock() {
             waker.wake_by_ref()
         }
     }
+}
+
+struct AsyncSignal {
+    slots: Mutex<Vec<Weak<Slot>>>,
+}
+
+impl AsyncSignal {
+    pub fn new() -> Self {
+        Self {
+            slots: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Wakes every receiver, and forgets those which have been dropped
+    pub fn wake(&self) {
+        self.slots.lock().retain(|slot| match slot.upgrade() {
+            Some(slot) => {
+                slot.wake();
+                true
+            }
+            None => false,
+        })
+    }
 
-    pub fn set_waker(&self, waker: Waker) {
-        *self.waker.lock() = Some(waker)
+    fn receiver(self: &Arc<Self>, woken: bool) -> NotifyReceiver {
+        let slot = Arc::new(Slot::new(woken));
+        self.slots.lock().push(Arc::downgrade(&slot));
+
+        NotifyReceiver {
+            signal: self.clone(),
+            slot,
+        }
     }
 }
 
+/// Completes when notified by the linked [`NotifySender`].
+///
+/// Each clone is notified separately, starting out with the pending notification of the
+/// receiver it was cloned from, if any.
 pub struct NotifyReceiver {
     signal: Arc<AsyncSignal>,
+    slot: Arc<Slot>,
+}
+
+impl NotifyReceiver {
+    /// Turns the receiver into a stream which yields an item for each notification, such as to
+    /// await changes in a `select!` loop.
+    ///
+    /// Notifications are coalesced: however many arrive before the stream is polled again, they
+    /// produce a single item. An item therefore means that something changed since the previous
+    /// one, not how many times. The stream never ends.
+    pub fn into_stream(mut self) -> impl Stream<Item = ()> + Send + Unpin {
+        futures::stream::poll_fn(move |cx| Pin::new(&mut self).poll(cx).map(Some))
+    }
+}
+
+impl Clone for NotifyReceiver {
+    fn clone(&self) -> Self {
+        self.signal.receiver(self.slot.woken.load(SeqCst))
+    }
 }
 
 impl Future for NotifyReceiver {
@@ -48,15 +103,17 @@ impl Future for NotifyReceiver {
         self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Self::Output> {
-        if self
-            .signal
+        let slot = &self.slot;
+        // Store the waker before checking, so that a notification in between is not missed
+        *slot.waker.lock() = Some(cx.waker().clone());
+
+        if slot
             .woken
             .compare_exchange(true, false, Ordering::Acquire, Ordering::Relaxed)
             .is_ok()
         {
             Poll::Ready(())
         } else {
-            self.signal.set_waker(cx.waker().clone());
             Poll::Pending
         }
     }
@@ -71,3 +128,111 @@ impl NotifySender {
         self.signal.wake()
     }
 }
+
+/// Wakes the receiver on every world change, and unsubscribes once the receiver is dropped
+impl EventHandler<ChangeEvent> for NotifySender {
+    fn on_event(&self, _: ChangeEvent) -> bool {
+        self.notify();
+        Arc::strong_count(&self.signal) > 1
+    }
+}
+
+/// Ends a subscription made by [`AppRef::scoped_subscription`](crate::app::AppRef::scoped_subscription)
+/// when dropped, even if the receiver is kept around.
+#[derive(Debug)]
+pub struct SubscriptionGuard {
+    active: Arc<AtomicBool>,
+}
+
+impl Drop for SubscriptionGuard {
+    fn drop(&mut self) {
+        self.active.store(false, SeqCst)
+    }
+}
+
+/// A [`NotifySender`] which unsubscribes once its [`SubscriptionGuard`] is dropped
+pub(crate) struct ScopedSender {
+    sender: NotifySender,
+    active: Arc<AtomicBool>,
+}
+
+impl EventHandler<ChangeEvent> for ScopedSender {
+    fn on_event(&self, event: ChangeEvent) -> bool {
+        self.active.load(SeqCst) && self.sender.on_event(event)
+    }
+}
+
+/// Ties `sender` to the returned guard
+pub(crate) fn scoped(sender: NotifySender) -> (ScopedSender, SubscriptionGuard) {
+    let active = Arc::new(AtomicBool::new(true));
+    (
+        ScopedSender {
+            sender,
+            active: active.clone(),
+        },
+        SubscriptionGuard { active },
+    )
+}
+
+/// Creates a linked sender and receiver.
+///
+/// This is a lightweight alternative to [`tokio::sync::Notify`]. Notifications sent while a
+/// receiver is not awaited are coalesced into one, which completes the next await of that
+/// receiver immediately. Clone the receiver to wait in several tasks at once.
+pub fn channel() -> (NotifySender, NotifyReceiver) {
+    let signal = Arc::new(AsyncSignal::new());
+    let rx = signal.receiver(false);
+    (NotifySender { signal }, rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{FutureExt, StreamExt};
+
+    use super::*;
+
+    #[test]
+    fn notifications_coalesce() {
+        let (tx, mut rx) = channel();
+        assert_eq!((&mut rx).now_or_never(), None);
+
+        tx.notify();
+        tx.notify();
+        assert_eq!((&mut rx).now_or_never(), Some(()));
+        assert_eq!((&mut rx).now_or_never(), None);
+    }
+
+    #[tokio::test]
+    async fn clones_are_all_woken() {
+        let (tx, rx) = channel();
+        let waiting = (0..3).map(|_| tokio::spawn(rx.clone())).collect::<Vec<_>>();
+        tokio::task::yield_now().await;
+
+        tx.notify();
+        for task in waiting {
+            tokio::time::timeout(std::time::Duration::from_secs(1), task)
+                .await
+                .unwrap()
+                .unwrap();
+        }
+
+        // The original receiver was notified as well
+        assert_eq!(rx.now_or_never(), Some(()));
+    }
+
+    #[test]
+    fn stream_coalesces() {
+        let (tx, rx) = channel();
+        let mut changes = rx.into_stream();
+        assert_eq!(changes.next().now_or_never(), None);
+
+        tx.notify();
+        tx.notify();
+        tx.notify();
+        assert_eq!(changes.next().now_or_never(), Some(Some(())));
+        assert_eq!(changes.next().now_or_never(), None);
+
+        tx.notify();
+        assert_eq!(changes.next().now_or_never(), Some(Some(())));
+    }
+}
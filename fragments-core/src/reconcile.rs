@@ -0,0 +1,256 @@
+//! Keyed reconciliation of the children of a fragment.
+//!
+//! Children are matched to keys through the keys and children of the previous reconciliation,
+//! which allows a keyed list to be updated by only spawning, despawning and reordering the
+//! children which changed.
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+use flax::Entity;
+
+use crate::{app::Event, components::order, Fragment, Widget, WidgetFuture};
+
+/// A change which turns one list of keys into another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyedOp<K> {
+    /// The key was removed
+    Despawn(K),
+    /// The key was added at the index
+    Spawn(K, usize),
+    /// The key was kept, but is now at the index
+    Move(K, usize),
+}
+
+/// Returns the operations which turn `old` into `new`.
+///
+/// Despawns come first, followed by the spawns and moves in the order of `new`. Keys which keep
+/// their index are left alone.
+pub fn diff_keys<K>(old: &[K], new: &[K]) -> Vec<KeyedOp<K>>
+where
+    K: Eq + Hash + Clone,
+{
+    let old_index = old
+        .iter()
+        .enumerate()
+        .map(|(i, key)| (key, i))
+        .collect::<HashMap<_, _>>();
+    let kept = new.iter().collect::<HashSet<_>>();
+
+    let despawns = old
+        .iter()
+        .filter(|key| !kept.contains(key))
+        .map(|key| KeyedOp::Despawn(key.clone()));
+
+    let updates = new
+        .iter()
+        .enumerate()
+        .filter_map(|(i, key)| match old_index.get(key) {
+            None => Some(KeyedOp::Spawn(key.clone(), i)),
+            Some(&prev) if prev != i => Some(KeyedOp::Move(key.clone(), i)),
+            Some(_) => None,
+        });
+
+    despawns.chain(updates).collect()
+}
+
+/// The outcome of [`reconcile`]
+pub struct Reconciled<'w, K, T> {
+    /// The operations which were applied
+    pub ops: Vec<KeyedOp<K>>,
+    /// The futures of the newly spawned widgets, which need to be driven by the caller
+    pub spawned: Vec<(K, WidgetFuture<'w, T>)>,
+    /// The child of each key, in the new order. Pass this to the next [`reconcile`] of the parent
+    pub children: Vec<(K, Entity)>,
+}
+
+/// Updates the keyed children of `parent`, which are given with their keys in order, to match
+/// the keyed widgets of `new`.
+///
+/// Only widgets with new keys are mounted. Children whose key is gone are despawned, and the
+/// remaining children are reordered. Other children of `parent` are left alone.
+///
+/// Keys must be unique.
+pub fn reconcile<'w, K, W>(
+    parent: &mut Fragment,
+    old: &[(K, Entity)],
+    new: Vec<(K, W)>,
+) -> Reconciled<'w, K, W::Output>
+where
+    K: Eq + Hash + Clone,
+    W: 'w + Widget,
+{
+    let mut ids = old.iter().cloned().collect::<HashMap<K, Entity>>();
+    let old_keys = old.iter().map(|(key, _)| key.clone()).collect::<Vec<_>>();
+    let new_keys = new.iter().map(|(key, _)| key.clone()).collect::<Vec<_>>();
+    let ops = diff_keys(&old_keys, &new_keys);
+
+    let mut widgets = new.into_iter().collect::<HashMap<_, _>>();
+    let mut spawned = Vec::new();
+    for op in &ops {
+        match op {
+            KeyedOp::Despawn(key) => {
+                let id = ids.remove(key).unwrap();
+                parent.app().enqueue(Event::Despawn(id)).ok();
+            }
+            KeyedOp::Spawn(key, index) => {
+                let widget = widgets.remove(key).expect("Duplicate key");
+                let child = parent.attach(widget);
+                parent
+                    .app()
+                    .world()
+                    .set(child.id(), order(), *index as u32)
+                    .unwrap();
+                ids.insert(key.clone(), child.id());
+                spawned.push((key.clone(), child));
+            }
+            KeyedOp::Move(key, index) => {
+                parent
+                    .app()
+                    .world()
+                    .set(ids[key], order(), *index as u32)
+                    .unwrap();
+            }
+        }
+    }
+
+    let children = new_keys
+        .into_iter()
+        .map(|key| {
+            let id = ids[&key];
+            (key, id)
+        })
+        .collect();
+
+    Reconciled {
+        ops,
+        spawned,
+        children,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    use crate::{app::App, components::content, widgets::Text, WidgetExt};
+
+    use super::*;
+
+    #[test]
+    fn reorder_and_append() {
+        let ops = diff_keys(&['a', 'b', 'c'], &['c', 'b', 'a', 'd']);
+        assert_eq!(
+            ops,
+            [
+                KeyedOp::Move('c', 0),
+                KeyedOp::Move('a', 2),
+                KeyedOp::Spawn('d', 3)
+            ]
+        );
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let widgets = |keys: &[char]| {
+            keys.iter()
+                .map(|&key| (key, Text::new(key.to_string())))
+                .collect::<Vec<_>>()
+        };
+
+        let mut initial = reconcile(&mut root, &[], widgets(&['a', 'b', 'c']));
+        for (_, child) in initial.spawned.drain(..) {
+            child.now_or_never().unwrap();
+        }
+        let old_children = root.children();
+
+        let result = reconcile(&mut root, &initial.children, widgets(&['c', 'b', 'a', 'd']));
+        assert_eq!(result.ops, ops);
+        assert_eq!(result.spawned.len(), 1);
+        for (_, child) in result.spawned {
+            child.now_or_never().unwrap();
+        }
+
+        let children = root.children();
+        assert_eq!(
+            &children[..3],
+            [old_children[2], old_children[1], old_children[0]]
+        );
+
+        let world = handle.world();
+        let text = children
+            .iter()
+            .map(|&id| world.get(id, content()).unwrap().clone())
+            .collect::<String>();
+        assert_eq!(text, "cbad");
+    }
+
+    #[test]
+    fn keyed_widgets_reuse_children() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let items = |names: &[&'static str]| {
+            names
+                .iter()
+                .map(|&name| Text::new(name).with_key(name))
+                .collect::<Vec<_>>()
+        };
+
+        let first = items(&["x", "y"]);
+        assert_eq!(
+            first.iter().map(|w| *w.key()).collect::<Vec<_>>(),
+            ["x", "y"]
+        );
+
+        let mut initial = reconcile(&mut root, &[], first.into_iter().map(Into::into).collect());
+        for (_, child) in initial.spawned.drain(..) {
+            child.now_or_never().unwrap();
+        }
+        let old_children = root.children();
+
+        let result = reconcile(
+            &mut root,
+            &initial.children,
+            items(&["y", "x"]).into_iter().map(Into::into).collect(),
+        );
+        assert!(result.spawned.is_empty());
+        assert_eq!(root.children(), [old_children[1], old_children[0]]);
+    }
+
+    #[test]
+    fn other_children_keep_their_place() {
+        let (handle, events) = App::new().start();
+        let mut events = Box::pin(events);
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        // Not reconciled, and ordered before the keyed children
+        let header = root.attach(Text::new("header"));
+        let header_id = header.id();
+        handle.world().set(header_id, order(), 0).unwrap();
+
+        let widgets = |keys: &[char]| {
+            keys.iter()
+                .map(|&key| (key, Text::new(key.to_string())))
+                .collect::<Vec<_>>()
+        };
+
+        let mut first = reconcile(&mut root, &[], widgets(&['a', 'b']));
+        let spawned = first.spawned.drain(..).collect::<Vec<_>>();
+        let b = first.children[1].1;
+
+        // The despawn of `a` is still pending when reconciling again
+        let second = reconcile(&mut root, &first.children, widgets(&['b', 'c']));
+        assert_eq!(second.children[0], ('b', b));
+        let third = reconcile(&mut root, &second.children, widgets(&['c']));
+        assert_eq!(third.ops, [KeyedOp::Despawn('b'), KeyedOp::Move('c', 0)]);
+
+        drop((spawned, second.spawned, third.spawned));
+        assert!(events.as_mut().now_or_never().is_none());
+        assert!(handle.world().is_alive(header_id));
+        assert!(!handle.world().is_alive(b));
+    }
+}
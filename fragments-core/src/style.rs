@@ -0,0 +1,386 @@
+use flax::{component, entity_ids, Component, ComponentKey, Entity, Query, World};
+
+use crate::components::{background, class, style, widget};
+
+component! {
+    /// The style and background last set by [`apply_stylesheet`], which are reset once the widget
+    /// no longer matches any rule
+    stylesheet_applied: (Style, Option<Color>),
+}
+
+/// A backend independent color
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    Grey,
+    Rgb { r: u8, g: u8, b: u8 },
+}
+
+/// Visual attributes of text.
+///
+/// Unset colors fall back to the terminal's or renderer's defaults.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn fg(mut self, color: Color) -> Self {
+        self.fg = Some(color);
+        self
+    }
+
+    pub fn bg(mut self, color: Color) -> Self {
+        self.bg = Some(color);
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    pub fn italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+
+    pub fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+}
+
+/// A run of text sharing a single style
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+impl Span {
+    pub fn new(text: impl Into<String>, style: Style) -> Self {
+        Self {
+            text: text.into(),
+            style,
+        }
+    }
+}
+
+impl From<&str> for Span {
+    fn from(text: &str) -> Self {
+        Self::new(text, Style::default())
+    }
+}
+
+impl From<String> for Span {
+    fn from(text: String) -> Self {
+        Self::new(text, Style::default())
+    }
+}
+
+/// A single line of text made up of differently styled spans
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RichContent(pub Vec<Span>);
+
+impl RichContent {
+    /// Returns the text without any styling
+    pub fn plain(&self) -> String {
+        self.0.iter().map(|span| span.text.as_str()).collect()
+    }
+}
+
+impl FromIterator<Span> for RichContent {
+    fn from_iter<I: IntoIterator<Item = Span>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+/// Selects the widgets a [`StyleRule`] applies to
+#[derive(Debug, Clone)]
+pub enum Selector {
+    /// Widgets with the class in their [`class`] component
+    Class(String),
+    /// Widgets with the tag, such as [`focused`](crate::components::focused) or
+    /// [`disabled`](crate::components::disabled)
+    State(Component<()>),
+}
+
+impl Selector {
+    fn matches(&self, world: &World, id: Entity) -> bool {
+        match self {
+            Selector::Class(name) => world
+                .get(id, class())
+                .is_ok_and(|classes| classes.contains(name)),
+            Selector::State(state) => world.has(id, *state),
+        }
+    }
+}
+
+/// Styling applied to the widgets matching a selector
+#[derive(Debug, Clone)]
+pub struct StyleRule {
+    pub selector: Selector,
+    pub style: Style,
+    pub background: Option<Color>,
+}
+
+impl StyleRule {
+    pub fn new(selector: Selector) -> Self {
+        Self {
+            selector,
+            style: Style::default(),
+            background: None,
+        }
+    }
+
+    pub fn fg(mut self, color: Color) -> Self {
+        self.style.fg = Some(color);
+        self
+    }
+
+    pub fn bg(mut self, color: Color) -> Self {
+        self.style.bg = Some(color);
+        self
+    }
+
+    pub fn background(mut self, color: Color) -> Self {
+        self.background = Some(color);
+        self
+    }
+}
+
+/// Rules which style widgets by their [`class`] and state, rather than each widget setting its
+/// own [`style`].
+///
+/// When several rules match a widget, later rules take precedence for the properties they set.
+#[derive(Debug, Default, Clone)]
+pub struct Stylesheet {
+    rules: Vec<StyleRule>,
+}
+
+impl Stylesheet {
+    pub fn new(rules: impl IntoIterator<Item = StyleRule>) -> Self {
+        Self {
+            rules: rules.into_iter().collect(),
+        }
+    }
+
+    /// Returns the components the selectors of the rules depend on
+    pub(crate) fn selector_keys(&self) -> Vec<ComponentKey> {
+        let mut keys = vec![class().key()];
+        for rule in &self.rules {
+            if let Selector::State(state) = rule.selector {
+                keys.push(state.key());
+            }
+        }
+
+        keys
+    }
+
+    /// Returns the style and background of the rules matching `id`, if any match
+    fn resolve(&self, world: &World, id: Entity) -> Option<(Style, Option<Color>)> {
+        let mut matched = self
+            .rules
+            .iter()
+            .filter(|rule| rule.selector.matches(world, id))
+            .peekable();
+
+        matched.peek()?;
+
+        Some(
+            matched.fold((Style::default(), None), |(style, background), rule| {
+                let style = Style {
+                    fg: rule.style.fg.or(style.fg),
+                    bg: rule.style.bg.or(style.bg),
+                    bold: style.bold || rule.style.bold,
+                    italic: style.italic || rule.style.italic,
+                    underline: style.underline || rule.style.underline,
+                };
+
+                (style, rule.background.or(background))
+            }),
+        )
+    }
+}
+
+/// Sets the [`style`] and [`background`] of every widget matched by the stylesheet.
+///
+/// Widgets which no longer match any rule, such as after losing a class or focus, have the values
+/// set by the stylesheet removed again, unless they were overwritten since.
+///
+/// Values are only written when they differ, to not trigger change subscribers needlessly.
+pub fn apply_stylesheet(world: &mut World, stylesheet: &Stylesheet) {
+    let resolved = Query::new(entity_ids())
+        .with(widget())
+        .borrow(world)
+        .iter()
+        .filter_map(|id| {
+            let resolved = stylesheet.resolve(world, id);
+            let applied = world.get(id, stylesheet_applied()).ok().map(|v| *v);
+            (resolved.is_some() || applied.is_some()).then_some((id, resolved, applied))
+        })
+        .collect::<Vec<_>>();
+
+    for (id, resolved, applied) in resolved {
+        let (applied_style, applied_color) = applied.unzip();
+        let applied_color = applied_color.flatten();
+
+        let Some((resolved, color)) = resolved else {
+            if world.get(id, style()).ok().as_deref() == applied_style.as_ref() {
+                world.remove(id, style()).unwrap();
+            }
+            if applied_color.is_some()
+                && world.get(id, background()).ok().as_deref() == applied_color.as_ref()
+            {
+                world.remove(id, background()).unwrap();
+            }
+            world.remove(id, stylesheet_applied()).unwrap();
+            continue;
+        };
+
+        if world.get(id, style()).ok().as_deref() != Some(&resolved) {
+            world.set(id, style(), resolved).unwrap();
+        }
+
+        let current = world.get(id, background()).ok().map(|v| *v);
+        match color {
+            Some(color) if current != Some(color) => {
+                world.set(id, background(), color).unwrap();
+            }
+            None if applied_color.is_some() && current == applied_color => {
+                world.remove(id, background()).unwrap();
+            }
+            _ => {}
+        }
+
+        if applied != Some((resolved, color)) {
+            world
+                .set(id, stylesheet_applied(), (resolved, color))
+                .unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+    use glam::{ivec2, uvec2, vec2};
+
+    use crate::{
+        app::{App, Event},
+        canvas::{draw_widgets, Canvas},
+        components::{content, position},
+        Fragment,
+    };
+
+    use super::*;
+
+    #[test]
+    fn class_applies_rule() {
+        let stylesheet =
+            Stylesheet::new([StyleRule::new(Selector::Class("danger".into())).fg(Color::Red)]);
+        let (handle, events) = App::new().with_stylesheet(stylesheet).start();
+        let mut events = Box::pin(events);
+
+        let mut fragment = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        fragment
+            .write()
+            .set(position(), vec2(0.0, 0.0))
+            .set(content(), "Error".into());
+
+        handle
+            .enqueue(Event::set_component(
+                fragment.id(),
+                class(),
+                vec!["danger".into()],
+            ))
+            .unwrap();
+        assert!(events.as_mut().now_or_never().is_none());
+
+        let world = handle.world();
+        assert_eq!(
+            world.get(fragment.id(), style()).unwrap().fg,
+            Some(Color::Red)
+        );
+
+        let mut canvas = Canvas::new(uvec2(5, 1));
+        draw_widgets(&world, &mut canvas);
+        assert_eq!(canvas.cell(ivec2(0, 0)).unwrap().style.fg, Some(Color::Red));
+    }
+
+    #[test]
+    fn removed_class_resets_style() {
+        let stylesheet = Stylesheet::new([StyleRule::new(Selector::Class("danger".into()))
+            .fg(Color::Red)
+            .background(Color::Black)]);
+        let (handle, events) = App::new().with_stylesheet(stylesheet).start();
+        let mut events = Box::pin(events);
+
+        let fragment = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let id = fragment.id();
+        let set_class = |classes: Vec<String>| {
+            handle
+                .enqueue(Event::set_component(id, class(), classes))
+                .unwrap();
+        };
+
+        set_class(vec!["danger".into()]);
+        assert!(events.as_mut().now_or_never().is_none());
+        assert_eq!(
+            handle.world().get(id, background()).as_deref(),
+            Ok(&Color::Black)
+        );
+
+        set_class(Vec::new());
+        assert!(events.as_mut().now_or_never().is_none());
+        let world = handle.world();
+        assert!(!world.has(id, style()));
+        assert!(!world.has(id, background()));
+    }
+
+    #[test]
+    fn set_focus_restyles() {
+        use crate::{components::focused, events::set_focus};
+
+        let stylesheet =
+            Stylesheet::new([StyleRule::new(Selector::State(focused())).fg(Color::Yellow)]);
+        let (handle, events) = App::new().with_stylesheet(stylesheet).start();
+        let mut events = Box::pin(events);
+
+        let spawn = || Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let (first, second) = (spawn(), spawn());
+        let (a, b) = (first.id(), second.id());
+        let fg = |id| {
+            handle
+                .world()
+                .get(id, style())
+                .ok()
+                .and_then(|style| style.fg)
+        };
+
+        // No event is sent, the change of focus alone wakes the event loop
+        set_focus(&mut handle.world(), a);
+        assert!(events.as_mut().now_or_never().is_none());
+        assert_eq!(fg(a), Some(Color::Yellow));
+
+        set_focus(&mut handle.world(), b);
+        assert!(events.as_mut().now_or_never().is_none());
+        assert_eq!((fg(a), fg(b)), (None, Some(Color::Yellow)));
+    }
+}
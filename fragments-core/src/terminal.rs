@@ -0,0 +1,892 @@
+//! Input and output for terminal applications.
+use std::{
+    io::{self, Stdout, Write},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{Event, EventStream, KeyCode, KeyEvent, KeyModifiers, MouseEvent},
+    queue,
+    style::{
+        self, Attribute, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+    },
+    terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType},
+};
+use flax::{
+    component, entity_ids,
+    events::{ChangeSubscriber, SubscriberFilterExt},
+    Component, Entity, FetchExt, Query, World,
+};
+use futures::{FutureExt, Stream, StreamExt};
+use glam::{ivec2, uvec2, IVec2, UVec2, Vec2};
+use tokio::sync::Notify;
+
+use crate::{
+    batch::Coalesced,
+    canvas::{cell_width, draw_widgets, Canvas},
+    components::{
+        background, canvas, content, cursor_pos, cursor_visible, dirty_rect, focused, headless,
+        hidden, layer, position, rich_content, size, style, validation, viewport, widget, z_index,
+    },
+    events::{focused_entity, send_event, send_event_to, EventHook, EventHooks},
+    fragment::root_of,
+    geometry::Rect,
+    layout::absolute_position,
+    style::{Color, Style},
+    throttle::render_paced,
+    Fragment, Widget,
+};
+
+component! {
+    /// Invoked for every key press
+    pub on_key: EventHooks<KeyEvent>,
+    /// Invoked for key presses which produce a character
+    pub on_char: EventHooks<char>,
+    pub on_mouse: EventHooks<MouseEvent>,
+    /// Invoked with the new size of the terminal
+    pub on_terminal_resize: EventHooks<UVec2>,
+}
+
+/// Owns the terminal's input and distributes it to the [`on_key`], [`on_char`], [`on_mouse`] and
+/// [`on_terminal_resize`] hooks of the widgets.
+///
+/// Key presses go to the [`focused`] widget only, or to every widget while none has focus, such
+/// that typing into one input does not reach the others. Mouse and resize events go to every
+/// widget.
+///
+/// Resizing the terminal also updates the [`viewport`] of the root fragment, unless the app is
+/// [`headless`].
+///
+/// There should only be one input layer per application, as terminal events are consumed when
+/// read.
+pub struct InputLayer<S> {
+    events: S,
+}
+
+impl InputLayer<EventStream> {
+    /// Reads events from the terminal
+    pub fn new() -> Self {
+        Self {
+            events: EventStream::new(),
+        }
+    }
+}
+
+impl Default for InputLayer<EventStream> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> InputLayer<S>
+where
+    S: Stream<Item = io::Result<Event>>,
+{
+    /// Reads events from an arbitrary stream, such as synthetic events in tests
+    pub fn from_stream(events: S) -> Self {
+        Self { events }
+    }
+}
+
+#[async_trait]
+impl<S> Widget for InputLayer<S>
+where
+    S: Stream<Item = io::Result<Event>> + Send + Unpin,
+{
+    type Output = io::Result<()>;
+
+    async fn mount(mut self, fragment: Fragment) -> io::Result<()> {
+        let app = fragment.app().clone();
+
+        while let Some(event) = self.events.next().await {
+            let mut world = app.world();
+            match event? {
+                Event::Key(key) => {
+                    if let KeyCode::Char(c) = key.code {
+                        if !key
+                            .modifiers
+                            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+                        {
+                            send_to_focused(&world, on_char(), c);
+                        }
+                    }
+
+                    send_to_focused(&world, on_key(), key);
+                }
+                Event::Mouse(mouse) => {
+                    send_event(&world, on_mouse(), mouse);
+                }
+                Event::Resize(width, height) => {
+                    let size = uvec2(width as _, height as _);
+                    send_event(&world, on_terminal_resize(), size);
+
+                    let root = root_of(&world, fragment.id());
+                    if !world.has(root, headless()) {
+                        world.set(root, viewport(), size.as_vec2()).unwrap();
+                    }
+                }
+                Event::FocusGained | Event::FocusLost | Event::Paste(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Sends a keyboard event to the [`focused`] widget, or to every widget while none has focus
+fn send_to_focused<T: Sync>(world: &World, event: Component<EventHooks<T>>, event_data: T)
+where
+    EventHook<T>: 'static,
+{
+    match focused_entity(world) {
+        Some(id) => {
+            send_event_to(world, id, event, event_data);
+        }
+        None => {
+            send_event(world, event, event_data);
+        }
+    }
+}
+
+impl From<Color> for style::Color {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Black => style::Color::Black,
+            Color::Red => style::Color::DarkRed,
+            Color::Green => style::Color::DarkGreen,
+            Color::Yellow => style::Color::DarkYellow,
+            Color::Blue => style::Color::DarkBlue,
+            Color::Magenta => style::Color::DarkMagenta,
+            Color::Cyan => style::Color::DarkCyan,
+            Color::White => style::Color::White,
+            Color::Grey => style::Color::Grey,
+            Color::Rgb { r, g, b } => style::Color::Rgb { r, g, b },
+        }
+    }
+}
+
+fn queue_style(w: &mut impl Write, style: Style) -> io::Result<()> {
+    queue!(w, SetAttribute(Attribute::Reset), ResetColor)?;
+
+    if let Some(fg) = style.fg {
+        queue!(w, SetForegroundColor(fg.into()))?;
+    }
+    if let Some(bg) = style.bg {
+        queue!(w, SetBackgroundColor(bg.into()))?;
+    }
+    if style.bold {
+        queue!(w, SetAttribute(Attribute::Bold))?;
+    }
+    if style.italic {
+        queue!(w, SetAttribute(Attribute::Italic))?;
+    }
+    if style.underline {
+        queue!(w, SetAttribute(Attribute::Underlined))?;
+    }
+
+    Ok(())
+}
+
+/// Writes the whole canvas to the terminal, starting at the top left corner.
+///
+/// Style changes are only emitted between cells of differing style.
+pub fn draw_canvas(w: &mut impl Write, canvas: &Canvas) -> io::Result<()> {
+    draw_canvas_region(w, canvas, Rect::new(Vec2::ZERO, canvas.size().as_vec2()))
+}
+
+/// Writes the cells of the canvas within `region` to the terminal, leaving the rest of the
+/// screen untouched.
+pub fn draw_canvas_region(w: &mut impl Write, canvas: &Canvas, region: Rect) -> io::Result<()> {
+    let min = region.pos.floor().as_ivec2().max(IVec2::ZERO);
+    let max = (region.pos + region.size)
+        .ceil()
+        .as_ivec2()
+        .min(canvas.size().as_ivec2());
+
+    let mut current = Style::default();
+    queue_style(w, current)?;
+
+    for y in min.y..max.y {
+        queue!(w, MoveTo(min.x as _, y as _))?;
+        // The terminal advances past the cells covered by a wide character by itself
+        let mut covered = 0;
+        for x in min.x..max.x {
+            if covered > 0 {
+                covered -= 1;
+                continue;
+            }
+
+            let (c, style) = canvas
+                .cell(ivec2(x, y))
+                .map_or((' ', Style::default()), |cell| (cell.c, cell.style));
+            covered = cell_width(c) - 1;
+
+            if style != current {
+                queue_style(w, style)?;
+                current = style;
+            }
+
+            queue!(w, Print(c))?;
+        }
+    }
+
+    queue_style(w, Style::default())?;
+    w.flush()
+}
+
+/// Removes the [`dirty_rect`]s of all widgets, and returns the screen space region covering them.
+///
+/// Returns `None` if nothing was marked as changed.
+pub fn take_dirty_region(world: &mut World) -> Option<Rect> {
+    let dirty = Query::new((entity_ids(), dirty_rect()))
+        .borrow(world)
+        .iter()
+        .map(|(id, &rect)| (id, rect.translate(absolute_position(world, id))))
+        .collect::<Vec<_>>();
+
+    for &(id, _) in &dirty {
+        world.remove(id, dirty_rect()).unwrap();
+    }
+
+    dirty.into_iter().map(|(_, rect)| rect).reduce(Rect::union)
+}
+
+/// Shows the terminal cursor where the focused widget wants it, or hides it if the focused widget
+/// does not have a visible cursor.
+///
+/// Call this after drawing, as drawing moves the cursor.
+pub fn draw_cursor(w: &mut impl Write, world: &World) -> io::Result<()> {
+    let cursor = Query::new((
+        entity_ids(),
+        cursor_pos().opt_or_default(),
+        cursor_visible(),
+    ))
+    .with(focused())
+    .borrow(world)
+    .iter()
+    .find(|&(_, _, &visible)| visible)
+    .map(|(id, &cursor, _)| (absolute_position(world, id) + cursor).as_uvec2());
+
+    match cursor {
+        Some(pos) => queue!(w, MoveTo(pos.x as _, pos.y as _), Show)?,
+        None => queue!(w, Hide)?,
+    }
+
+    w.flush()
+}
+
+/// Creates a canvas covering the whole terminal, or the [`viewport`] of the root of `id` when
+/// the app is [`headless`]
+pub fn screen_canvas(world: &World, id: Entity) -> io::Result<Canvas> {
+    let root = root_of(world, id);
+    let size = match world.get(root, viewport()) {
+        Ok(viewport) if world.has(root, headless()) => viewport.as_uvec2(),
+        _ => {
+            let (width, height) = crossterm::terminal::size()?;
+            uvec2(width as _, height as _)
+        }
+    };
+
+    Ok(Canvas::new(size))
+}
+
+/// Renders all widgets onto the [`screen_canvas`] and returns the rows separated by newlines.
+///
+/// Useful for snapshot tests of [`headless`] apps.
+pub fn render_to_string(world: &World, id: Entity) -> io::Result<String> {
+    let mut canvas = screen_canvas(world, id)?;
+    draw_widgets(world, &mut canvas);
+    Ok(canvas.to_string())
+}
+
+/// Disables raw mode when dropped
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        enable_raw_mode()?;
+        Ok(Self)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        disable_raw_mode().ok();
+    }
+}
+
+/// The part of the screen a frame of the [`TerminalBackend`] writes
+enum Redraw {
+    Full,
+    /// Only the union of the [`dirty_rect`]s, as no other widget changed
+    Region(Rect),
+    /// Nothing but the cursor, if it moved
+    Cursor,
+}
+
+/// Draws the widgets to the terminal whenever they change.
+///
+/// The [`screen_canvas`] is redrawn, at most once per interval, when a component which affects
+/// the output of a widget changes. When every change of a frame is covered by a [`dirty_rect`],
+/// only their region is written. The frame is written by [`render_paced`], off the async
+/// executor. Writing to stdout enables raw mode for as long as the backend is mounted.
+///
+/// Returns the first error writing to the target.
+pub struct TerminalBackend<W> {
+    target: W,
+    raw_mode: bool,
+    interval: Duration,
+}
+
+impl TerminalBackend<Stdout> {
+    /// Draws to stdout, in raw mode
+    pub fn new() -> Self {
+        Self {
+            target: io::stdout(),
+            raw_mode: true,
+            interval: Duration::from_millis(16),
+        }
+    }
+}
+
+impl Default for TerminalBackend<Stdout> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write + Send> TerminalBackend<W> {
+    /// Draws to `target` instead of stdout, such as a buffer in tests. Raw mode is left alone.
+    pub fn with_target(target: W) -> Self {
+        Self {
+            target,
+            raw_mode: false,
+            interval: Duration::from_millis(16),
+        }
+    }
+
+    /// Sets the least time between two redraws, which is ~60 times a second by default
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+#[async_trait]
+impl<W: Write + Send + 'static> Widget for TerminalBackend<W> {
+    type Output = io::Result<()>;
+
+    async fn mount(self, fragment: Fragment) -> io::Result<()> {
+        let drawn = [
+            position().key(),
+            size().key(),
+            content().key(),
+            rich_content().key(),
+            style().key(),
+            canvas().key(),
+            background().key(),
+            validation().key(),
+            layer().key(),
+            z_index().key(),
+            hidden().key(),
+            viewport().key(),
+            focused().key(),
+            cursor_pos().key(),
+            cursor_visible().key(),
+            dirty_rect().key(),
+        ];
+
+        let changed = Arc::new(Notify::new());
+        // Changes which are not covered by a dirty rect
+        let full = Arc::new(Notify::new());
+        {
+            let mut world = fragment.app().world();
+            world.subscribe(
+                ChangeSubscriber::new(&drawn, Coalesced::new(Arc::downgrade(&changed)))
+                    .filter(widget().with()),
+            );
+            world.subscribe(
+                ChangeSubscriber::new(&drawn, Coalesced::new(Arc::downgrade(&full)))
+                    .filter(widget().with() & dirty_rect().without()),
+            );
+        }
+        // The first frame draws everything
+        full.notify_one();
+
+        let _raw_mode = self.raw_mode.then(RawMode::enable).transpose()?;
+
+        let id = fragment.id();
+        let mut target = self.target;
+        let mut last_cursor = Vec::new();
+        render_paced(
+            fragment.app(),
+            &changed,
+            self.interval,
+            |world| {
+                let dirty = take_dirty_region(world);
+                let redraw = match dirty {
+                    _ if full.notified().now_or_never().is_some() => Redraw::Full,
+                    Some(region) => Redraw::Region(region),
+                    None => Redraw::Cursor,
+                };
+
+                let mut screen = screen_canvas(world, id)?;
+                draw_widgets(world, &mut screen);
+
+                let mut cursor = Vec::new();
+                draw_cursor(&mut cursor, world)?;
+                Ok((redraw, screen, cursor))
+            },
+            move |frame: io::Result<(Redraw, Canvas, Vec<u8>)>| {
+                let (redraw, screen, cursor) = frame?;
+
+                let w = &mut target;
+                match redraw {
+                    Redraw::Full => {
+                        queue!(w, Clear(ClearType::All))?;
+                        draw_canvas(w, &screen)?;
+                    }
+                    Redraw::Region(region) => draw_canvas_region(w, &screen, region)?,
+                    Redraw::Cursor if cursor == last_cursor => return Ok(()),
+                    Redraw::Cursor => {}
+                }
+                w.write_all(&cursor)?;
+                last_cursor = cursor;
+                w.flush()
+            },
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{MouseButton, MouseEventKind};
+    use futures::{stream, FutureExt};
+    use parking_lot::Mutex;
+
+    use crate::{
+        app::App,
+        components::{content, position},
+    };
+
+    use super::*;
+
+    /// Collects the output, while the backend owns the writer
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Output {
+        fn take(&self) -> String {
+            String::from_utf8(std::mem::take(&mut *self.0.lock())).unwrap()
+        }
+    }
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn hooks_receive_events() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let keys = Arc::new(Mutex::new(Vec::new()));
+        let chars = Arc::new(Mutex::new(String::new()));
+        let clicks = Arc::new(Mutex::new(0));
+
+        {
+            let keys = keys.clone();
+            let chars = chars.clone();
+            let clicks = clicks.clone();
+            root.write()
+                .on_event(on_key(), move |_, _: &World, key: &KeyEvent| {
+                    keys.lock().push(key.code)
+                })
+                .on_event(on_char(), move |_, _: &World, &c: &char| {
+                    chars.lock().push(c)
+                })
+                .on_event(on_mouse(), move |_, _: &World, _: &MouseEvent| {
+                    *clicks.lock() += 1
+                });
+        }
+
+        let events = stream::iter([
+            Event::Key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE)),
+            Event::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column: 1,
+                row: 2,
+                modifiers: KeyModifiers::NONE,
+            }),
+        ])
+        .map(Ok);
+
+        root.attach(InputLayer::from_stream(events))
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            *keys.lock(),
+            [KeyCode::Char('a'), KeyCode::Char('c'), KeyCode::Enter]
+        );
+        assert_eq!(*chars.lock(), "a");
+        assert_eq!(*clicks.lock(), 1);
+    }
+
+    #[test]
+    fn keys_go_to_focused_widget() {
+        let app = App::new();
+        let handle = app.handle();
+        let spawn = || Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let (mut first, mut second) = (spawn(), spawn());
+
+        let typed = Arc::new(Mutex::new(Vec::new()));
+        for (fragment, name) in [(&mut first, "first"), (&mut second, "second")] {
+            let typed = typed.clone();
+            fragment
+                .write()
+                .on_event(on_char(), move |_, _: &World, &c: &char| {
+                    typed.lock().push((name, c))
+                });
+        }
+
+        handle.world().set(second.id(), focused(), ()).unwrap();
+
+        let events = stream::iter([Event::Key(KeyEvent::new(
+            KeyCode::Char('a'),
+            KeyModifiers::NONE,
+        ))])
+        .map(Ok);
+        first
+            .attach(InputLayer::from_stream(events))
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(*typed.lock(), [("second", 'a')]);
+    }
+
+    #[test]
+    fn rich_text_styles() {
+        use crate::{components::position, style::Span, widgets::Text};
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let bold = Style::new().bold();
+        let red = Style::new().fg(Color::Red);
+        root.put(Text::rich([Span::new("ab", bold), Span::new("c", red)]))
+            .now_or_never()
+            .unwrap();
+        root.write().set(position(), glam::Vec2::ZERO);
+
+        let mut canvas = Canvas::new(uvec2(4, 1));
+        draw_widgets(&handle.world(), &mut canvas);
+
+        assert_eq!(canvas.to_string(), "abc ");
+        let style = |x| canvas.cell(ivec2(x, 0)).unwrap().style;
+        assert_eq!([style(0), style(1), style(2)], [bold, bold, red]);
+
+        let mut out = Vec::new();
+        draw_canvas(&mut out, &canvas).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let bold_at = out.find("\x1b[1ma").unwrap();
+        let red_at = out.find("\x1b[38;5;1mc").unwrap();
+        assert!(bold_at < red_at);
+    }
+
+    #[tokio::test]
+    async fn headless_row() {
+        use crate::widgets::{Row, Text};
+
+        struct Snapshot(Arc<Mutex<String>>);
+
+        #[async_trait]
+        impl Widget for Snapshot {
+            type Output = ();
+
+            async fn mount(self, mut fragment: Fragment) {
+                let mut row = fragment
+                    .attach(Row::new((Text::new("Hello"), Text::new("World"))).with_padding(1.0));
+                assert!((&mut row).now_or_never().is_none());
+
+                let world = fragment.app().world();
+                *self.0.lock() = render_to_string(&world, fragment.id()).unwrap();
+            }
+        }
+
+        let output = Arc::new(Mutex::new(String::new()));
+        App::new()
+            .with_viewport(glam::vec2(80.0, 24.0))
+            .run(Snapshot(output.clone()))
+            .await
+            .unwrap();
+
+        let output = output.lock();
+        let rows = output.lines().collect::<Vec<_>>();
+        assert_eq!(rows.len(), 24);
+        assert!(rows.iter().all(|row| row.len() == 80));
+        assert_eq!(rows[0].trim_end(), "Hello World");
+        assert!(rows[1..].iter().all(|row| row.trim().is_empty()));
+    }
+
+    #[tokio::test]
+    async fn backend_redraws_on_change() {
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        root.write()
+            .set(viewport(), glam::vec2(10.0, 2.0))
+            .set(headless(), ())
+            .set(position(), glam::Vec2::ZERO)
+            .set(content(), "Hello".into());
+
+        let output = Output::default();
+        let backend = root.attach(
+            TerminalBackend::with_target(output.clone()).with_interval(Duration::from_millis(1)),
+        );
+        let backend = tokio::spawn(backend);
+
+        let settle = || tokio::time::sleep(Duration::from_millis(20));
+        let take = || output.take();
+
+        settle().await;
+        assert!(take().contains("Hello"));
+
+        root.write().set(content(), "World".into());
+        settle().await;
+        assert!(take().contains("World"));
+
+        // Nothing changed, so nothing is drawn
+        settle().await;
+        assert_eq!(take(), "");
+
+        // Restyling alone redraws the same text
+        root.write().set(style(), Style::new().bold());
+        settle().await;
+        assert!(take().contains("\x1b[1mW"));
+
+        backend.abort();
+    }
+
+    #[test]
+    fn background_fills_size() {
+        use crate::components::{background, size};
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        root.write()
+            .set(position(), glam::vec2(1.0, 0.0))
+            .set(size(), glam::vec2(3.0, 2.0))
+            .set(background(), Color::Blue);
+
+        let mut canvas = Canvas::new(uvec2(5, 3));
+        draw_widgets(&handle.world(), &mut canvas);
+
+        let filled = (0..3)
+            .flat_map(|y| (0..5).map(move |x| ivec2(x, y)))
+            .filter(|&pos| canvas.cell(pos).and_then(|cell| cell.style.bg) == Some(Color::Blue))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            filled,
+            [
+                ivec2(1, 0),
+                ivec2(2, 0),
+                ivec2(3, 0),
+                ivec2(1, 1),
+                ivec2(2, 1),
+                ivec2(3, 1)
+            ]
+        );
+
+        let mut out = Vec::new();
+        draw_canvas(&mut out, &canvas).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        // Each row enters the background once, and leaves it after three cells
+        let bg = SetBackgroundColor(Color::Blue.into()).to_string();
+        assert_eq!(out.matches(&format!("{bg}   ")).count(), 2);
+    }
+
+    #[test]
+    fn focused_input_shows_cursor() {
+        use crate::{components::focused, widgets::TextInput};
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let input = root.attach(TextInput::new("abc"));
+        let id = input.id();
+        let input = input.now_or_never();
+        assert!(input.is_none());
+
+        let draw = || {
+            let mut out = Vec::new();
+            draw_cursor(&mut out, &handle.world()).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!(draw(), "\x1b[?25l");
+
+        handle.world().set(id, focused(), ()).unwrap();
+        handle
+            .world()
+            .set(id, position(), glam::vec2(2.0, 1.0))
+            .unwrap();
+
+        // 1-based row 2, column 6
+        assert_eq!(draw(), "\x1b[2;6H\x1b[?25h");
+    }
+
+    #[test]
+    fn wide_input_cursor() {
+        use crate::{components::focused, widgets::TextInput};
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let mut input = root.attach(TextInput::new("漢字"));
+        let id = input.id();
+        assert!((&mut input).now_or_never().is_none());
+
+        let mut world = handle.world();
+        world.set(id, focused(), ()).unwrap();
+        world.set(id, position(), glam::Vec2::ZERO).unwrap();
+        assert_eq!(*world.get(id, cursor_pos()).unwrap(), glam::vec2(4.0, 0.0));
+
+        let mut canvas = Canvas::new(uvec2(5, 1));
+        draw_widgets(&world, &mut canvas);
+        let mut out = Vec::new();
+        draw_canvas(&mut out, &canvas).unwrap();
+        draw_cursor(&mut out, &world).unwrap();
+
+        // The terminal moves past the second cell of each character by itself
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("漢字 "));
+        assert!(out.ends_with("\x1b[1;5H\x1b[?25h"));
+    }
+
+    #[test]
+    fn invalid_input_shows_error() {
+        use crate::{
+            components::{focused, validation},
+            widgets::TextInput,
+        };
+
+        let (handle, events) = App::new().start();
+        let mut events = Box::pin(events);
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let mut input = root.attach(TextInput::new("4").with_validator(|text| {
+            text.parse::<u32>()
+                .map(|_| ())
+                .map_err(|_| "Not a number".into())
+        }));
+        let id = input.id();
+        assert!((&mut input).now_or_never().is_none());
+
+        let mut world = handle.world();
+        world.set(id, focused(), ()).unwrap();
+        world.set(id, position(), glam::vec2(0.0, 0.0)).unwrap();
+        send_event(&world, on_char(), 'x');
+        drop(world);
+        assert!(events.as_mut().now_or_never().is_none());
+
+        let world = handle.world();
+        assert_eq!(
+            *world.get(id, validation()).unwrap(),
+            Err("Not a number".into())
+        );
+
+        let mut canvas = Canvas::new(uvec2(12, 2));
+        draw_widgets(&world, &mut canvas);
+        assert_eq!(canvas.to_string(), "4x          \nNot a number");
+        assert_eq!(canvas.cell(ivec2(0, 1)).unwrap().style.fg, Some(Color::Red));
+    }
+
+    #[tokio::test]
+    async fn dirty_rect_limits_redraw() {
+        use crate::widgets::Text;
+
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        root.write()
+            .set(viewport(), glam::vec2(10.0, 3.0))
+            .set(headless(), ());
+
+        // Kept alive, as dropping the future would despawn the text
+        let mut clock = root.attach(Text::new("12:00"));
+        (&mut clock).now_or_never();
+        handle
+            .world()
+            .set(clock.id(), position(), glam::vec2(2.0, 1.0))
+            .unwrap();
+
+        let output = Output::default();
+        let backend = tokio::spawn(root.attach(
+            TerminalBackend::with_target(output.clone()).with_interval(Duration::from_millis(1)),
+        ));
+
+        let settle = || tokio::time::sleep(Duration::from_millis(20));
+        settle().await;
+        assert!(output.take().contains("12:00"));
+
+        // Only the minutes change
+        handle
+            .world()
+            .set(
+                clock.id(),
+                dirty_rect(),
+                Rect::new(glam::vec2(3.0, 0.0), glam::vec2(2.0, 1.0)),
+            )
+            .unwrap();
+        handle
+            .world()
+            .set(clock.id(), content(), "12:34".into())
+            .unwrap();
+        settle().await;
+
+        let mut expected = Vec::new();
+        queue_style(&mut expected, Style::default()).unwrap();
+        queue!(expected, MoveTo(5, 1), Print('3'), Print('4')).unwrap();
+        queue_style(&mut expected, Style::default()).unwrap();
+        queue!(expected, Hide).unwrap();
+        assert_eq!(output.take(), String::from_utf8(expected).unwrap());
+        assert!(!handle.world().has(clock.id(), dirty_rect()));
+
+        // Without a dirty rect, everything is redrawn
+        handle
+            .world()
+            .set(clock.id(), content(), "13:00".into())
+            .unwrap();
+        settle().await;
+        let out = output.take();
+        assert!(out.contains(&Clear(ClearType::All).to_string()));
+        assert!(out.contains("13:00"));
+
+        backend.abort();
+    }
+}
@@ -0,0 +1,168 @@
+//! Helpers for driving an app end-to-end without a real terminal or window.
+use std::sync::MutexGuard;
+
+use flax::{components::name, Component, Entity, World};
+
+use crate::{
+    app::{App, AppRef},
+    events::{send_event, EventHooks},
+    fragment::{children_of, parent_of},
+    Fragment, Widget,
+};
+
+/// An app with a root fragment, for tests which inspect the tree.
+///
+/// Entity ids differ between runs, so entities are instead referred to by logical names. A
+/// fragment with a [`name`] is called by it, and any other fragment by its parent's logical name
+/// and its index among its siblings, e.g; `root/0/2`.
+pub struct TestApp {
+    app: AppRef,
+    root: Fragment,
+}
+
+impl TestApp {
+    pub fn new() -> Self {
+        let app = App::new().handle();
+        let root = Fragment::spawn(&mut app.world(), app.clone(), None);
+        Self { app, root }
+    }
+
+    /// The root fragment, to attach the widgets under test to
+    pub fn root(&mut self) -> &mut Fragment {
+        &mut self.root
+    }
+
+    pub fn world(&self) -> MutexGuard<'_, World> {
+        self.app.world()
+    }
+
+    /// Returns the logical name of `id`, if it is part of the tree
+    pub fn name_of(&self, id: Entity) -> Option<String> {
+        logical_name(&self.world(), self.root.id(), id)
+    }
+
+    /// Returns the entity with the logical name `target`
+    pub fn id_of(&self, target: &str) -> Option<Entity> {
+        find_logical(&self.world(), self.root.id(), "root".into(), target)
+    }
+}
+
+impl Default for TestApp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn find_logical(world: &World, id: Entity, path: String, target: &str) -> Option<Entity> {
+    if path == target {
+        return Some(id);
+    }
+
+    children_of(world, id)
+        .into_iter()
+        .enumerate()
+        .find_map(|(i, child)| {
+            let path = match world.get(child, name()) {
+                Ok(name) => name.clone(),
+                Err(_) => format!("{path}/{i}"),
+            };
+
+            find_logical(world, child, path, target)
+        })
+}
+
+fn logical_name(world: &World, root: Entity, id: Entity) -> Option<String> {
+    if let Ok(name) = world.get(id, name()) {
+        return Some(name.clone());
+    }
+
+    if id == root {
+        return Some("root".into());
+    }
+
+    let parent = parent_of(world, id)?;
+    let index = children_of(world, parent)
+        .into_iter()
+        .position(|child| child == id)?;
+
+    Some(format!("{}/{index}", logical_name(world, root, parent)?))
+}
+
+/// An input injected into the app by [`run_scripted`]
+pub struct ScriptedEvent {
+    send: Box<dyn FnOnce(&World) + Send>,
+}
+
+impl ScriptedEvent {
+    /// Sends `data` to all hooks of `event`, such as a key press or a resize
+    pub fn new<T>(event: Component<EventHooks<T>>, data: T) -> Self
+    where
+        T: 'static + Send + Sync,
+    {
+        Self {
+            send: Box::new(move |world| {
+                send_event(world, event, data);
+            }),
+        }
+    }
+}
+
+/// Describes why a scripted run stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptExit<T> {
+    /// The root widget completed
+    Completed(T),
+    /// The app received [`Event::Exit`](crate::app::Event::Exit)
+    Exited,
+    /// The app was still running after all steps were taken
+    Exhausted,
+}
+
+/// The outcome of [`run_scripted`]
+pub struct ScriptOutput<T> {
+    pub exit: ScriptExit<T>,
+    app: AppRef,
+}
+
+impl<T> ScriptOutput<T> {
+    /// Returns the world as it was when the run stopped
+    pub fn world(&self) -> MutexGuard<'_, World> {
+        self.app.world()
+    }
+}
+
+/// Runs `root` in a new app, feeding one of `inputs` into the event system each step.
+///
+/// Between each step the app's tasks are allowed to make progress. The run stops when the root
+/// completes, the app exits, or after `max_steps` steps, whichever comes first.
+pub async fn run_scripted<W: Widget>(
+    root: W,
+    inputs: Vec<ScriptedEvent>,
+    max_steps: usize,
+) -> eyre::Result<ScriptOutput<W::Output>> {
+    let (app, handle_events) = App::new().start();
+
+    let fragment = Fragment::spawn(&mut app.world(), app.clone(), None);
+    let root = root.mount(fragment);
+
+    let script = async {
+        let mut inputs = inputs.into_iter();
+        for _ in 0..max_steps {
+            tokio::task::yield_now().await;
+            if let Some(input) = inputs.next() {
+                (input.send)(&app.world());
+            }
+        }
+    };
+
+    let exit = tokio::select! {
+        output = root => ScriptExit::Completed(output),
+        result = handle_events => {
+            result?;
+            ScriptExit::Exited
+        }
+        _ = script => ScriptExit::Exhausted,
+    };
+
+    Ok(ScriptOutput { exit, app })
+}
@@ -0,0 +1,177 @@
+//! Rate limiting of redraws.
+use std::time::Duration;
+
+use flax::World;
+use futures::FutureExt;
+use tokio::{sync::Notify, time::Instant};
+
+use crate::app::AppRef;
+
+/// Caps how often a renderer redraws in response to change notifications.
+///
+/// Notifications arriving within `interval` of the previous redraw are coalesced into a single
+/// catch-up redraw once the interval has elapsed, so the final state is never dropped.
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    interval: Duration,
+    last: Option<Instant>,
+}
+
+impl Throttle {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: None,
+        }
+    }
+
+    /// Waits until `changed` is notified and the interval since the previous redraw has elapsed
+    pub async fn wait(&mut self, changed: &Notify) {
+        changed.notified().await;
+
+        if let Some(last) = self.last {
+            tokio::time::sleep_until(last + self.interval).await;
+        }
+
+        // Changes made while waiting are covered by this redraw
+        changed.notified().now_or_never();
+
+        self.last = Some(Instant::now());
+    }
+}
+
+/// Redraws whenever `changed` is notified, at most once per `interval`.
+///
+/// Each frame is captured by `capture` while the world is locked, which should be cheap, such as
+/// drawing onto a [`Canvas`](crate::canvas::Canvas). The frame is then presented by `present` on
+/// the blocking thread pool, so a slow terminal or GPU holds up neither the world nor the async
+/// tasks handling input.
+///
+/// Returns the first error of `present`.
+pub async fn render_paced<T, E>(
+    app: &AppRef,
+    changed: &Notify,
+    interval: Duration,
+    mut capture: impl FnMut(&mut World) -> T,
+    mut present: impl FnMut(T) -> Result<(), E> + Send + 'static,
+) -> Result<(), E>
+where
+    T: Send + 'static,
+    E: Send + 'static,
+{
+    let mut throttle = Throttle::new(interval);
+    loop {
+        let frame = capture(&mut app.world());
+
+        let presented = tokio::task::spawn_blocking(move || {
+            let result = present(frame);
+            (present, result)
+        })
+        .await;
+
+        let result;
+        (present, result) = match presented {
+            Ok(presented) => presented,
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        };
+        result?;
+
+        throttle.wait(changed).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn redraws_bounded_by_interval() {
+        let changed = Arc::new(Notify::new());
+        let redraws = Arc::new(AtomicUsize::new(0));
+
+        let renderer = tokio::spawn({
+            let changed = changed.clone();
+            let redraws = redraws.clone();
+            async move {
+                let mut throttle = Throttle::new(Duration::from_millis(20));
+                loop {
+                    throttle.wait(&changed).await;
+                    redraws.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        });
+
+        // A burst of changes every millisecond for 100ms
+        for _ in 0..100 {
+            changed.notify_one();
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let count = redraws.load(Ordering::Relaxed);
+        assert!((5..=7).contains(&count), "{count} redraws");
+
+        // The catch-up redraw has happened, and nothing is pending
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(redraws.load(Ordering::Relaxed), count);
+
+        renderer.abort();
+    }
+
+    #[tokio::test]
+    async fn input_handled_during_slow_present() {
+        use std::convert::Infallible;
+
+        use crate::{
+            app::{App, Event},
+            components::content,
+        };
+
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+        let id = handle.world().spawn();
+
+        let changed = Notify::new();
+        let renderer = async {
+            render_paced(
+                &handle,
+                &changed,
+                Duration::from_millis(16),
+                |_| (),
+                |()| {
+                    std::thread::sleep(Duration::from_millis(300));
+                    Ok::<_, Infallible>(())
+                },
+            )
+            .await
+        };
+
+        let input = async {
+            // Let the first frame start presenting
+            tokio::task::yield_now().await;
+
+            let start = Instant::now();
+            handle
+                .enqueue(Event::set_component(id, content(), "Typed".into()))
+                .unwrap();
+
+            while !handle.world().has(id, content()) {
+                tokio::task::yield_now().await;
+            }
+
+            start.elapsed()
+        };
+
+        let latency = tokio::select! {
+            latency = input => latency,
+            _ = renderer => unreachable!(),
+        };
+
+        assert!(latency < Duration::from_millis(100), "{latency:?}");
+    }
+}
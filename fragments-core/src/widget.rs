@@ -1,42 +1,123 @@
-use std::sync::MutexGuard;
+use std::{any::Any, hash::Hash, panic::AssertUnwindSafe, sync::Arc};
 
 use async_trait::async_trait;
-use flax::{child_of, Component, ComponentValue, Entity, World};
-use futures::{future::BoxFuture, Future, FutureExt};
+use flax::Entity;
+use futures::{future::BoxFuture, stream::FuturesUnordered, Future, FutureExt, StreamExt};
 
-use crate::{app::AppRef, components::widget, fragment::Fragment};
+use crate::{
+    app::{AppRef, Event},
+    fragment::{Fragment, FragmentRef},
+};
 
 /// Represents a widget which can be rendered into a fragment of the UI tree.
 ///
 /// Widgets can optionally return a value, which can be used for Input fields or alike.
 #[async_trait]
-pub trait Widget: Send {
+pub trait Widget: Send + MountDyn {
     type Output;
     /// Mounts the widget, returning a future which updates and keeps track of the state.
     async fn mount(self, fragment: Fragment) -> Self::Output;
 }
 
+/// A widget which does all of its work upfront, without awaiting anything.
+///
+/// Mount it inline using [`Fragment::put_sync`], or wrap it in [`Immediate`] to use it where a
+/// [`Widget`] is expected.
+pub trait SyncWidget {
+    fn mount_sync(self, fragment: &mut FragmentRef);
+}
+
+/// A widget which renders from props, and can be rendered again with new props in place.
+///
+/// See [`Fragment::mount_stateful_handle`].
+pub trait StatefulWidget: Send {
+    type Props;
+
+    /// Renders `props` into the fragment. Called once when mounted, and again for every update.
+    fn update(&mut self, fragment: &mut FragmentRef, props: Self::Props);
+}
+
+/// Adapts a [`SyncWidget`] into a [`Widget`].
+///
+/// This is a wrapper rather than a blanket impl as the latter would overlap with the impls for
+/// boxed and shared widgets.
+pub struct Immediate<W>(pub W);
+
 #[async_trait]
-pub(crate) trait BoxedWidget: Send {
-    type Output;
-    async fn mount_boxed(self: Box<Self>, fragment: Fragment) -> Self::Output;
+impl<W> Widget for Immediate<W>
+where
+    W: SyncWidget + Send,
+{
+    type Output = ();
+
+    async fn mount(self, mut fragment: Fragment) {
+        self.0.mount_sync(&mut fragment.write())
+    }
+}
+
+/// A widget defined by an async function. See [`fn_widget`]
+pub struct FnWidget<F>(F);
+
+/// Turns an async function or closure taking the fragment into a widget, which saves declaring
+/// a type for a one-off widget. The output of the function is the output of the widget.
+pub fn fn_widget<F, Fut>(f: F) -> FnWidget<F>
+where
+    F: FnOnce(Fragment) -> Fut + Send,
+    Fut: Future + Send,
+{
+    FnWidget(f)
 }
 
 #[async_trait]
-impl<W> BoxedWidget for W
+impl<F, Fut> Widget for FnWidget<F>
 where
-    W: ?Sized + Widget,
+    F: FnOnce(Fragment) -> Fut + Send,
+    Fut: Future + Send,
+    Fut::Output: Send + 'static,
 {
-    type Output = W::Output;
+    type Output = Fut::Output;
 
-    async fn mount_boxed(self: Box<Self>, fragment: Fragment) -> W::Output {
-        (self).mount(fragment).await
+    async fn mount(self, fragment: Fragment) -> Self::Output {
+        (self.0)(fragment).await
     }
 }
 
+/// Mounts a widget behind a `Box<dyn Widget>`, which can not be moved out of its box.
+///
+/// Implemented for every widget. The output is type erased, as `Widget` can not name its own
+/// output in a supertrait and remain object safe.
+#[doc(hidden)]
+pub trait MountDyn: Send {
+    fn mount_dyn<'a>(self: Box<Self>, fragment: Fragment) -> BoxFuture<'a, Box<dyn Any + Send>>
+    where
+        Self: 'a;
+}
+
+impl<W> MountDyn for W
+where
+    W: Widget,
+    W::Output: Send + 'static,
+{
+    fn mount_dyn<'a>(self: Box<Self>, fragment: Fragment) -> BoxFuture<'a, Box<dyn Any + Send>>
+    where
+        Self: 'a,
+    {
+        Box::pin(async move { Box::new((*self).mount(fragment).await) as Box<dyn Any + Send> })
+    }
+}
+
+/// A future which drives a widget attached to a child fragment.
+///
+/// Dropping the future before it completes cancels the widget and despawns the child fragment
+/// along with its descendants.
+///
+/// Nested widget futures are dropped before the fragment they belong to is despawned, so a
+/// cancelled subtree is despawned depth-first, deepest children first.
 pub struct WidgetFuture<'a, T = ()> {
-    fut: BoxFuture<'a, T>,
+    fut: Option<BoxFuture<'a, T>>,
     id: Entity,
+    name: Option<&'static str>,
+    app: AppRef,
 }
 
 impl<'a, T> Future for WidgetFuture<'a, T> {
@@ -46,29 +127,218 @@ impl<'a, T> Future for WidgetFuture<'a, T> {
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Self::Output> {
-        self.fut.poll_unpin(cx)
+        let fut = self
+            .fut
+            .as_mut()
+            .expect("WidgetFuture polled after completion");
+
+        let output = futures::ready!(fut.poll_unpin(cx));
+        self.fut = None;
+        self.app.lifecycle.complete(self.id);
+        std::task::Poll::Ready(output)
+    }
+}
+
+impl<'a, T> std::fmt::Debug for WidgetFuture<'a, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WidgetFuture")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("completed", &self.fut.is_none())
+            .finish()
+    }
+}
+
+impl<'a, T> Drop for WidgetFuture<'a, T> {
+    fn drop(&mut self) {
+        if let Some(fut) = self.fut.take() {
+            // Drop the nested widgets first, which enqueues the despawn of the deeper fragments
+            // before this one
+            drop(fut);
+            self.app.enqueue(Event::Despawn(self.id)).ok();
+        }
     }
 }
 
 impl<'a, T> WidgetFuture<'a, T> {
-    pub(crate) fn new(id: Entity, fut: BoxFuture<'a, T>) -> Self {
-        Self { fut, id }
+    pub(crate) fn new(
+        id: Entity,
+        app: AppRef,
+        name: Option<&'static str>,
+        fut: BoxFuture<'a, T>,
+    ) -> Self {
+        Self {
+            fut: Some(fut),
+            id,
+            name,
+            app,
+        }
     }
 
     pub fn id(&self) -> Entity {
         self.id
     }
+
+    /// Name used to tell widgets apart in diagnostics, the widget's type name by default
+    pub fn name(&self) -> Option<&'static str> {
+        self.name
+    }
+
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
 }
 
 #[async_trait]
 impl<W> Widget for Box<W>
 where
     W: ?Sized + Widget,
+    W::Output: Send + 'static,
+{
+    type Output = W::Output;
+
+    async fn mount(self, frag: Fragment) -> Self::Output {
+        let output = self.mount_dyn(frag).await;
+        *output
+            .downcast()
+            .expect("MountDyn is only implemented with the widget's own output")
+    }
+}
+
+/// Allows a single widget template to be shared and mounted in many places.
+///
+/// The inner widget is cloned for each mount, unless this is the last reference.
+#[async_trait]
+impl<W> Widget for Arc<W>
+where
+    W: Widget + Clone + Sync,
+    W::Output: Send + 'static,
 {
     type Output = W::Output;
 
     async fn mount(self, frag: Fragment) -> Self::Output {
-        self.mount_boxed(frag).await
+        Arc::unwrap_or_clone(self).mount(frag).await
+    }
+}
+
+/// Combinators available on every widget
+pub trait WidgetExt: Widget + Sized {
+    /// Runs `f` with the fragment just before the widget is mounted
+    fn tap<F>(self, f: F) -> Tap<Self, F>
+    where
+        F: FnOnce(&Fragment) + Send,
+    {
+        Tap { widget: self, f }
+    }
+
+    /// Gives the widget a key, which identifies it as the same element across renders.
+    ///
+    /// A keyed widget converts into the `(key, widget)` pairs consumed by
+    /// [`crate::reconcile::reconcile`], so a child is reused for as long as its key is.
+    fn with_key<K: Hash + Eq>(self, key: K) -> Keyed<Self, K> {
+        Keyed { widget: self, key }
+    }
+
+    /// Mounts `fallback` in place of the widget if the widget panics.
+    ///
+    /// The fallback is mounted in the same fragment, after it has been cleared of everything the
+    /// panicking widget left behind. A panic while the world is locked poisons the world, which
+    /// can not be recovered from.
+    fn catch<F>(self, fallback: F) -> Catch<Self, F>
+    where
+        F: Widget<Output = Self::Output>,
+    {
+        Catch {
+            widget: self,
+            fallback,
+        }
+    }
+}
+
+impl<W: Widget> WidgetExt for W {}
+
+/// See [`WidgetExt::catch`]
+pub struct Catch<W, F> {
+    widget: W,
+    fallback: F,
+}
+
+#[async_trait]
+impl<W, F> Widget for Catch<W, F>
+where
+    W: Widget,
+    F: Widget<Output = W::Output>,
+    W::Output: Send + 'static,
+{
+    type Output = W::Output;
+
+    async fn mount(self, mut fragment: Fragment) -> Self::Output {
+        let mounted = AssertUnwindSafe(self.widget.mount(fragment.duplicate()))
+            .catch_unwind()
+            .await;
+
+        match mounted {
+            Ok(output) => output,
+            Err(_) => {
+                tracing::error!(id = ?fragment.id(), "Widget panicked, mounting fallback");
+                fragment.write().clear();
+                self.fallback.mount(fragment).await
+            }
+        }
+    }
+}
+
+/// See [`WidgetExt::with_key`]
+pub struct Keyed<W, K> {
+    widget: W,
+    key: K,
+}
+
+impl<W, K> Keyed<W, K> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<W, K> From<Keyed<W, K>> for (K, W) {
+    fn from(keyed: Keyed<W, K>) -> Self {
+        (keyed.key, keyed.widget)
+    }
+}
+
+#[async_trait]
+impl<W, K> Widget for Keyed<W, K>
+where
+    W: Widget,
+    W::Output: Send + 'static,
+    K: Send,
+{
+    type Output = W::Output;
+
+    async fn mount(self, fragment: Fragment) -> Self::Output {
+        self.widget.mount(fragment).await
+    }
+}
+
+/// See [`WidgetExt::tap`]
+pub struct Tap<W, F> {
+    widget: W,
+    f: F,
+}
+
+#[async_trait]
+impl<W, F> Widget for Tap<W, F>
+where
+    W: Widget,
+    W::Output: Send + 'static,
+    F: FnOnce(&Fragment) + Send,
+{
+    type Output = W::Output;
+
+    async fn mount(self, fragment: Fragment) -> Self::Output {
+        (self.f)(&fragment);
+        self.widget.mount(fragment).await
     }
 }
 
@@ -78,8 +348,70 @@ pub trait WidgetCollection {
     fn attach(self, parent: &mut Fragment) -> Vec<WidgetFuture<'static>>;
 }
 
-impl WidgetCollection for Vec<Box<dyn Widget<Output = ()> + Send>> {
+/// Attaches the widgets in order.
+///
+/// Widgets of different types can be collected as `Box<dyn Widget<Output = ()> + Send>`, as a boxed
+/// widget is a widget as well.
+impl<W: Widget<Output = ()> + Send + 'static> WidgetCollection for Vec<W> {
     fn attach(self, parent: &mut Fragment) -> Vec<WidgetFuture<'static>> {
+        self.into_iter().map(|w| parent.attach(w)).collect()
+    }
+}
+
+/// Attaches the widgets in order
+impl<W: Widget<Output = ()> + Send + 'static, const N: usize> WidgetCollection for [W; N] {
+    fn attach(self, parent: &mut Fragment) -> Vec<WidgetFuture<'static>> {
+        self.into_iter().map(|w| parent.attach(w)).collect()
+    }
+}
+
+/// Helper trait for turning a list of fallible widgets into a list of render futures.
+pub trait TryWidgetCollection<E>: Sized {
+    /// Convert the collection into fragments
+    fn attach(self, parent: &mut Fragment) -> Vec<WidgetFuture<'static, Result<(), E>>>;
+
+    /// Attach the widgets and drive them to completion.
+    ///
+    /// If any widget fails, all fragments of the collection are despawned and the error is
+    /// returned.
+    fn try_attach(self, parent: &mut Fragment) -> BoxFuture<'static, Result<(), E>>
+    where
+        E: 'static + Send,
+    {
+        let app = parent.app().clone();
+        let mut futures = self
+            .attach(parent)
+            .into_iter()
+            .map(|fut| {
+                let id = fut.id();
+                fut.map(move |result| (id, result))
+            })
+            .collect::<FuturesUnordered<_>>();
+
+        async move {
+            let mut completed = Vec::new();
+            while let Some((id, result)) = futures.next().await {
+                completed.push(id);
+
+                if let Err(err) = result {
+                    // Cancelling the pending widgets despawns them
+                    drop(futures);
+                    app.enqueue_all(completed.into_iter().map(Event::Despawn))
+                        .ok();
+                    return Err(err);
+                }
+            }
+
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+impl<E: Send + 'static> TryWidgetCollection<E>
+    for Vec<Box<dyn Widget<Output = Result<(), E>> + Send>>
+{
+    fn attach(self, parent: &mut Fragment) -> Vec<WidgetFuture<'static, Result<(), E>>> {
         self.into_iter().map(|w| parent.attach_boxed(w)).collect()
     }
 }
@@ -91,6 +423,27 @@ macro_rules! tuple_impl {
                 vec![$( parent.attach(self.$idx),)*]
             }
         }
+
+        /// Mounts the widgets as siblings, each in a child of the fragment, without a container
+        /// which lays them out. Completes when all of them have completed.
+        #[async_trait]
+        impl<$($ty: Widget<Output = ()> + 'static + Send,)*> Widget for ($($ty,)*) {
+            type Output = ();
+
+            async fn mount(self, mut fragment: Fragment) {
+                let mut children = WidgetCollection::attach(self, &mut fragment)
+                    .into_iter()
+                    .collect::<FuturesUnordered<_>>();
+
+                while children.next().await.is_some() {}
+            }
+        }
+
+        impl<Err, $($ty: Widget<Output = Result<(), Err>> + 'static + Send,)*> TryWidgetCollection<Err> for ($($ty,)*) {
+            fn attach(self, parent: &mut Fragment) -> Vec<WidgetFuture<'static, Result<(), Err>>> {
+                vec![$( parent.attach(self.$idx),)*]
+            }
+        }
     };
 }
 
@@ -98,4 +451,277 @@ tuple_impl! { 0 => A }
 tuple_impl! { 0 => A, 1 => B }
 tuple_impl! { 0 => A, 1 => B, 2 => C }
 tuple_impl! { 0 => A, 1 => B, 2 => C, 3 => D }
+tuple_impl! { 0 => A, 1 => B, 2 => C, 3 => D, 4 => E }
+tuple_impl! { 0 => A, 1 => B, 2 => C, 3 => D, 4 => E, 5 => F }
+tuple_impl! { 0 => A, 1 => B, 2 => C, 3 => D, 4 => E, 5 => F, 6 => G }
+tuple_impl! { 0 => A, 1 => B, 2 => C, 3 => D, 4 => E, 5 => F, 6 => G, 7 => H }
+tuple_impl! { 0 => A, 1 => B, 2 => C, 3 => D, 4 => E, 5 => F, 6 => G, 7 => H, 8 => I }
+tuple_impl! { 0 => A, 1 => B, 2 => C, 3 => D, 4 => E, 5 => F, 6 => G, 7 => H, 8 => I, 9 => J }
+tuple_impl! { 0 => A, 1 => B, 2 => C, 3 => D, 4 => E, 5 => F, 6 => G, 7 => H, 8 => I, 9 => J, 10 => K }
+tuple_impl! { 0 => A, 1 => B, 2 => C, 3 => D, 4 => E, 5 => F, 6 => G, 7 => H, 8 => I, 9 => J, 10 => K, 11 => L }
+tuple_impl! { 0 => A, 1 => B, 2 => C, 3 => D, 4 => E, 5 => F, 6 => G, 7 => H, 8 => I, 9 => J, 10 => K, 11 => L, 12 => M }
+tuple_impl! { 0 => A, 1 => B, 2 => C, 3 => D, 4 => E, 5 => F, 6 => G, 7 => H, 8 => I, 9 => J, 10 => K, 11 => L, 12 => M, 13 => N }
+tuple_impl! { 0 => A, 1 => B, 2 => C, 3 => D, 4 => E, 5 => F, 6 => G, 7 => H, 8 => I, 9 => J, 10 => K, 11 => L, 12 => M, 13 => N, 14 => O }
+tuple_impl! { 0 => A, 1 => B, 2 => C, 3 => D, 4 => E, 5 => F, 6 => G, 7 => H, 8 => I, 9 => J, 10 => K, 11 => L, 12 => M, 13 => N, 14 => O, 15 => P }
+
+#[cfg(test)]
+mod tests {
+    use crate::{app::App, components::content, fragment::children_of, widgets::Text};
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct Label(String);
 
+    #[async_trait]
+    impl Widget for Label {
+        type Output = ();
+
+        async fn mount(self, mut fragment: Fragment) {
+            fragment.write().set(content(), self.0);
+        }
+    }
+
+    struct Fallible(Result<(), &'static str>);
+
+    #[async_trait]
+    impl Widget for Fallible {
+        type Output = Result<(), &'static str>;
+
+        async fn mount(self, _: Fragment) -> Self::Output {
+            self.0
+        }
+    }
+
+    struct Pending;
+
+    #[async_trait]
+    impl Widget for Pending {
+        type Output = Result<(), &'static str>;
+
+        async fn mount(self, _: Fragment) -> Self::Output {
+            futures::future::pending().await
+        }
+    }
+
+    #[test]
+    fn try_attach_despawns_siblings() {
+        let (handle, events) = App::new().start();
+        let mut events = Box::pin(events);
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let result = (Fallible(Ok(())), Fallible(Err("Failed")), Pending)
+            .try_attach(&mut root)
+            .now_or_never()
+            .unwrap();
+
+        assert_eq!(result, Err("Failed"));
+
+        let children = root.children();
+        assert_eq!(children.len(), 3);
+        assert!(events.as_mut().now_or_never().is_none());
+
+        let world = handle.world();
+        for id in children {
+            assert!(!world.is_alive(id));
+        }
+    }
+
+    #[test]
+    fn shared_template() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let template = Arc::new(Label("Shared".into()));
+
+        let a = root.attach(template.clone());
+        let b = root.attach(template);
+        let ids = [a.id(), b.id()];
+
+        assert!(a.now_or_never().is_some());
+        assert!(b.now_or_never().is_some());
+
+        let world = handle.world();
+        assert_ne!(ids[0], ids[1]);
+        for id in ids {
+            assert_eq!(*world.get(id, content()).unwrap(), "Shared");
+        }
+    }
+
+    #[test]
+    fn tap_before_mount() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let mut tapped = None;
+        let child = root.attach(Label("Hello".into()).tap(|fragment| {
+            assert!(!fragment.app().world().has(fragment.id(), content()));
+            tapped = Some(fragment.id());
+        }));
+
+        let id = child.id();
+        child.now_or_never().unwrap();
+
+        assert_eq!(tapped, Some(id));
+        assert!(handle.world().has(id, content()));
+    }
+
+    #[test]
+    fn tuple_mounts_siblings() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        root.put((Text::new("a"), Text::new("b")))
+            .now_or_never()
+            .unwrap();
+
+        let world = handle.world();
+        let text = children_of(&world, root.id())
+            .into_iter()
+            .map(|id| world.get(id, content()).unwrap().clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(text, ["a", "b"]);
+    }
+
+    #[test]
+    fn vec_of_one_widget_type() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let texts = vec![Text::new("a"), Text::new("b")];
+        for child in WidgetCollection::attach(texts, &mut root) {
+            child.now_or_never().unwrap();
+        }
+
+        let boxed: Vec<Box<dyn Widget<Output = ()> + Send>> =
+            vec![Box::new(Text::new("c")), Box::new(Label("d".into()))];
+        for child in WidgetCollection::attach(boxed, &mut root) {
+            child.now_or_never().unwrap();
+        }
+
+        let world = handle.world();
+        let text = children_of(&world, root.id())
+            .into_iter()
+            .map(|id| world.get(id, content()).unwrap().clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(text, ["a", "b", "c", "d"]);
+    }
+
+    #[tokio::test]
+    async fn closure_as_widget() {
+        let widget = fn_widget(|mut fragment: Fragment| async move {
+            fragment.write().set(content(), "hi".into());
+            tokio::task::yield_now().await;
+            fragment.id()
+        });
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let child = root.attach(widget);
+        let id = child.id();
+
+        assert_eq!(tokio::spawn(child).await.unwrap(), id);
+        assert_eq!(*handle.world().get(id, content()).unwrap(), "hi");
+    }
+
+    #[test]
+    fn array_keeps_order() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let children = WidgetCollection::attach(["a", "b", "c"].map(Text::new), &mut root);
+        let ids = children.iter().map(|child| child.id()).collect::<Vec<_>>();
+        for child in children {
+            child.now_or_never().unwrap();
+        }
+
+        let world = handle.world();
+        assert_eq!(children_of(&world, root.id()), ids);
+        let text = ids
+            .iter()
+            .map(|&id| world.get(id, content()).unwrap().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(text, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn future_named_after_widget() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let child = root.attach(Label("Hello".into()));
+        assert_eq!(child.name(), Some(std::any::type_name::<Label>()));
+        assert!(child.name().unwrap().ends_with("Label"));
+
+        let child = root.attach(Pending).with_name("pending");
+        assert_eq!(child.name(), Some("pending"));
+    }
+
+    #[tokio::test]
+    async fn attach_is_spawnable() {
+        fn assert_spawnable<F: Future + Send + 'static>(fut: F) -> F {
+            fut
+        }
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let child = tokio::spawn(assert_spawnable(root.attach(Label("Hello".into()))));
+        child.await.unwrap();
+
+        let world = handle.world();
+        let child = children_of(&world, root.id())[0];
+        assert_eq!(*world.get(child, content()).unwrap(), "Hello");
+    }
+
+    #[test]
+    fn catch_mounts_fallback() {
+        struct Panics;
+
+        #[async_trait]
+        impl Widget for Panics {
+            type Output = &'static str;
+
+            async fn mount(self, mut fragment: Fragment) -> &'static str {
+                fragment.write().set(content(), "Partial".into());
+                panic!("Failed to mount")
+            }
+        }
+
+        struct Fallback;
+
+        #[async_trait]
+        impl Widget for Fallback {
+            type Output = &'static str;
+
+            async fn mount(self, mut fragment: Fragment) -> &'static str {
+                assert!(!fragment.app().world().has(fragment.id(), content()));
+                fragment
+                    .write()
+                    .set(content(), "Something went wrong".into());
+                "fallback"
+            }
+        }
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let output = root.put(Panics.catch(Fallback)).now_or_never().unwrap();
+        assert_eq!(output, "fallback");
+        assert_eq!(
+            *handle.world().get(root.id(), content()).unwrap(),
+            "Something went wrong"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::pin::pin;
+
+use async_trait::async_trait;
+use flax::{entity_ids, events::ChangeSubscriber, Query};
+use futures::{stream, StreamExt};
+use futures_signals::signal::{Signal, SignalExt};
+use glam::{ivec2, IVec2, Vec2};
+
+use crate::{
+    batch::Coalesced,
+    canvas::Canvas,
+    components::{canvas, position, size, widget},
+    layout::absolute_position,
+    Fragment, Widget,
+};
+
+/// Draws a border with the entity id around the bounds of every sized widget.
+///
+/// The overlay is shown while `visible` is true, which allows toggling it from e.g; a key
+/// handler.
+pub struct DebugOverlay<S> {
+    visible: S,
+}
+
+impl<S> DebugOverlay<S>
+where
+    S: Signal<Item = bool>,
+{
+    pub fn new(visible: S) -> Self {
+        Self { visible }
+    }
+}
+
+#[async_trait]
+impl<S> Widget for DebugOverlay<S>
+where
+    S: 'static + Signal<Item = bool> + Send,
+{
+    type Output = ();
+
+    async fn mount(self, mut fragment: Fragment) {
+        let (tx, rx) = flume::unbounded();
+
+        {
+            let mut frag = fragment.write();
+            frag.set(position(), Vec2::ZERO);
+            frag.world_mut().subscribe(ChangeSubscriber::new(
+                &[position().key(), size().key()],
+                Coalesced::new(move |_| tx.send(None).is_ok()),
+            ));
+        }
+
+        let mut events = pin!(stream::select(
+            self.visible.to_stream().map(Some),
+            rx.into_stream(),
+        ));
+
+        let id = fragment.id();
+        let mut visible = false;
+        let mut query = Query::new((entity_ids(), position(), size())).with(widget());
+
+        while let Some(event) = events.next().await {
+            visible = event.unwrap_or(visible);
+
+            let mut frag = fragment.write();
+            if !visible {
+                frag.remove(canvas());
+                continue;
+            }
+
+            // Positions are relative to the parent, whereas the overlay covers the screen from
+            // its own origin
+            let world = frag.world();
+            let origin = absolute_position(world, id);
+            let bounds = query
+                .borrow(world)
+                .iter()
+                .map(|(id, _, &size)| {
+                    let pos = absolute_position(world, id) - origin;
+                    (id, pos.as_ivec2(), size.as_ivec2())
+                })
+                .collect::<Vec<_>>();
+
+            let extent = bounds
+                .iter()
+                .map(|&(_, pos, size)| pos + size)
+                .fold(IVec2::ZERO, IVec2::max)
+                .as_uvec2();
+
+            let mut overlay = Canvas::new(extent);
+            for (id, pos, size) in bounds {
+                overlay.draw_border(pos, size);
+
+                let label = id.to_string();
+                overlay.draw_str(
+                    pos + ivec2(1, 0),
+                    &label[..label.len().min((size.x - 2).max(0) as usize)],
+                );
+            }
+
+            frag.set(canvas(), overlay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+    use futures_signals::signal::always;
+    use glam::{uvec2, vec2};
+
+    use crate::{app::App, canvas::draw_widgets};
+
+    use super::*;
+
+    struct Placed;
+
+    #[async_trait]
+    impl Widget for Placed {
+        type Output = ();
+
+        async fn mount(self, mut fragment: Fragment) {
+            fragment
+                .write()
+                .set(position(), vec2(2.0, 1.0))
+                .set(size(), vec2(4.0, 3.0));
+        }
+    }
+
+    /// Offsets a [`Placed`] by its own position
+    struct Nested;
+
+    #[async_trait]
+    impl Widget for Nested {
+        type Output = ();
+
+        async fn mount(self, mut fragment: Fragment) {
+            fragment.write().set(position(), vec2(5.0, 3.0));
+            fragment.attach(Placed).await
+        }
+    }
+
+    #[test]
+    fn overlay_draws_bounds() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        assert!(root.attach(Placed).now_or_never().is_some());
+        let mut nested = root.attach(Nested);
+        (&mut nested).now_or_never();
+
+        let mut overlay = root.attach(DebugOverlay::new(always(true)));
+        assert!((&mut overlay).now_or_never().is_none());
+
+        let mut target = Canvas::new(uvec2(12, 8));
+        draw_widgets(&handle.world(), &mut target);
+
+        assert_eq!(target.get(ivec2(2, 1)), Some('┌'));
+        assert_eq!(target.get(ivec2(5, 1)), Some('┐'));
+        assert_eq!(target.get(ivec2(2, 3)), Some('└'));
+        assert_eq!(target.get(ivec2(5, 3)), Some('┘'));
+        assert_eq!(target.get(ivec2(1, 1)), None);
+
+        // Drawn where the nested widget is on screen, rather than at its relative position
+        assert_eq!(target.get(ivec2(7, 4)), Some('┌'));
+        assert_eq!(target.get(ivec2(10, 6)), Some('┘'));
+    }
+}
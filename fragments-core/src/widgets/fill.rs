@@ -0,0 +1,29 @@
+use async_trait::async_trait;
+use futures::future::ready;
+use futures_signals::signal::SignalExt;
+
+use crate::{
+    components::{constraints, size},
+    Fragment, Widget,
+};
+
+/// Takes up as much space as its [`constraints`] allow.
+///
+/// Along an unbounded axis, such as the axis of a [`Stack`](super::Stack), the minimum size is
+/// taken instead. This is useful for a separator or background which spans its container.
+pub struct Fill;
+
+#[async_trait]
+impl Widget for Fill {
+    type Output = ();
+
+    async fn mount(self, mut fragment: Fragment) {
+        fragment
+            .signal(constraints())
+            .for_each(|constraints| {
+                fragment.write().set(size(), constraints.fill());
+                ready(())
+            })
+            .await
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    hash::Hash,
+    pin::pin,
+};
+
+use async_trait::async_trait;
+use flax::Entity;
+use futures::{
+    future::{abortable, AbortHandle},
+    select,
+    stream::FuturesUnordered,
+    StreamExt,
+};
+use futures_signals::signal::{Signal, SignalExt};
+
+use crate::{
+    components::order,
+    reconcile::{diff_keys, KeyedOp},
+    Fragment, Widget,
+};
+
+/// A list of keyed widgets which follows a signal of items.
+///
+/// Each time the signal changes, the new items are diffed against the mounted children by key.
+/// Only items with a new key are mounted, children whose key is gone are detached, and the rest
+/// are left in place and reordered to match. The widgets of items which are already mounted are
+/// dropped.
+///
+/// Keys are expected to be unique within each set of items. Of several items with the same key,
+/// only the first is kept, and a warning is logged for the rest.
+pub struct List<S> {
+    items: S,
+}
+
+impl<S, I, K, W> List<S>
+where
+    S: Signal<Item = I>,
+    I: IntoIterator<Item = (K, W)>,
+{
+    pub fn new(items: S) -> Self {
+        Self { items }
+    }
+}
+
+#[async_trait]
+impl<S, I, K, W> Widget for List<S>
+where
+    S: Signal<Item = I> + Send,
+    I: IntoIterator<Item = (K, W)> + Send,
+    K: 'static + Hash + Eq + Clone + Send,
+    W: 'static + Widget<Output = ()>,
+{
+    type Output = ();
+
+    async fn mount(self, mut fragment: Fragment) {
+        let mut keys = Vec::new();
+        let mut children: HashMap<K, (Entity, AbortHandle)> = HashMap::new();
+        let mut running = FuturesUnordered::new();
+
+        let mut items = pin!(self.items.to_stream().fuse());
+        loop {
+            let items = select! {
+                items = items.next() => match items {
+                    Some(items) => items,
+                    None => break,
+                },
+                _ = running.select_next_some() => continue,
+            };
+
+            let mut new_keys = Vec::new();
+            let mut widgets = HashMap::new();
+            for (index, (key, widget)) in items.into_iter().enumerate() {
+                match widgets.entry(key) {
+                    Entry::Occupied(_) => {
+                        tracing::warn!(index, "Skipping list item with a duplicate key");
+                    }
+                    Entry::Vacant(slot) => {
+                        new_keys.push(slot.key().clone());
+                        slot.insert(widget);
+                    }
+                }
+            }
+
+            for op in diff_keys(&keys, &new_keys) {
+                match op {
+                    KeyedOp::Despawn(key) => {
+                        let (id, handle) = children.remove(&key).unwrap();
+                        handle.abort();
+                        fragment.detach(id).ok();
+                    }
+                    KeyedOp::Spawn(key, index) => {
+                        let widget = widgets.remove(&key).unwrap();
+                        let child = fragment.attach(widget);
+                        let id = child.id();
+                        fragment
+                            .app()
+                            .world()
+                            .set(id, order(), index as u32)
+                            .unwrap();
+
+                        let (child, handle) = abortable(child);
+                        running.push(child);
+                        children.insert(key, (id, handle));
+                    }
+                    KeyedOp::Move(key, index) => {
+                        fragment
+                            .app()
+                            .world()
+                            .set(children[&key].0, order(), index as u32)
+                            .unwrap();
+                    }
+                }
+            }
+
+            keys = new_keys;
+        }
+
+        // The items will not change again
+        while running.next().await.is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_signals::signal::Mutable;
+
+    use crate::{app::App, components::content, fragment::children_of, widgets::Text};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn keeps_unchanged_items() {
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+
+        let keys = Mutable::new(vec!["a", "b", "c"]);
+        let items = keys.signal_cloned().map(|keys| {
+            keys.into_iter()
+                .map(|key| (key, Text::new(key)))
+                .collect::<Vec<_>>()
+        });
+
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let list = root.attach(List::new(items));
+        let list_id = list.id();
+        tokio::spawn(list);
+
+        let settle = || tokio::time::sleep(std::time::Duration::from_millis(10));
+        let snapshot = || {
+            let world = handle.world();
+            children_of(&world, list_id)
+                .into_iter()
+                .map(|id| (world.get(id, content()).unwrap().clone(), id))
+                .collect::<Vec<_>>()
+        };
+
+        settle().await;
+        let first = snapshot();
+        assert_eq!(first.len(), 3);
+
+        // Remove one and append one
+        keys.set(vec!["a", "c", "d"]);
+        settle().await;
+        let second = snapshot();
+        assert_eq!(second[..2], [first[0].clone(), first[2].clone()]);
+        assert_eq!(second[2].0, "d");
+        assert!(!handle.world().is_alive(first[1].1));
+
+        // Reorder only
+        keys.set(vec!["d", "c", "a"]);
+        settle().await;
+        let third = snapshot();
+        assert_eq!(
+            third,
+            [second[2].clone(), second[1].clone(), second[0].clone()]
+        );
+    }
+
+    #[tokio::test]
+    async fn duplicate_keys_keep_first() {
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+
+        let items = Mutable::new(vec![("a", "first"), ("b", "b"), ("a", "second")]);
+        let items = items.signal_cloned().map(|items| {
+            items
+                .into_iter()
+                .map(|(key, text)| (key, Text::new(text)))
+                .collect::<Vec<_>>()
+        });
+
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let list = root.attach(List::new(items));
+        let list_id = list.id();
+        let list = tokio::spawn(list);
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        let world = handle.world();
+        let texts = children_of(&world, list_id)
+            .into_iter()
+            .map(|id| world.get(id, content()).unwrap().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["first", "b"]);
+        assert!(!list.is_finished());
+    }
+}
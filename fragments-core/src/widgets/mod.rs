@@ -0,0 +1,22 @@
+//! Reusable widgets built on top of the core components.
+mod debug_overlay;
+mod fill;
+mod list;
+mod progress_bar;
+mod resource;
+mod row;
+mod stack;
+mod tabs;
+mod text;
+mod text_input;
+
+pub use debug_overlay::*;
+pub use fill::*;
+pub use list::*;
+pub use progress_bar::*;
+pub use resource::*;
+pub use row::*;
+pub use stack::*;
+pub use tabs::*;
+pub use text::*;
+pub use text_input::*;
@@ -0,0 +1,110 @@
+use std::iter::repeat_n;
+
+use async_trait::async_trait;
+use futures::future::ready;
+use futures_signals::signal::{Signal, SignalExt};
+use glam::vec2;
+
+use crate::{
+    accessibility::Role,
+    components::{content, role, size},
+    Fragment, Widget,
+};
+
+const FILLED: char = '█';
+const EMPTY: char = '░';
+
+/// A determinate progress indicator.
+///
+/// Fills a portion of its width proportional to the progress signal, which is expected to be in
+/// the range `0..=1`. Values outside the range are clamped.
+pub struct ProgressBar<S> {
+    progress: S,
+    width: usize,
+}
+
+impl<S> ProgressBar<S>
+where
+    S: Signal<Item = f32>,
+{
+    /// Creates a new progress bar spanning `width` cells
+    pub fn new(width: usize, progress: S) -> Self {
+        Self { progress, width }
+    }
+}
+
+/// Returns the number of filled cells for the given progress
+fn filled_cells(progress: f32, width: usize) -> usize {
+    (progress.clamp(0.0, 1.0) * width as f32).round() as usize
+}
+
+#[async_trait]
+impl<S> Widget for ProgressBar<S>
+where
+    S: Signal<Item = f32> + Send,
+{
+    type Output = ();
+
+    async fn mount(self, mut fragment: Fragment) {
+        let width = self.width;
+        fragment
+            .write()
+            .set(size(), vec2(width as f32, 1.0))
+            .set(role(), Role::ProgressBar);
+
+        self.progress
+            .for_each(|progress| {
+                let filled = filled_cells(progress, width);
+
+                let bar = repeat_n(FILLED, filled)
+                    .chain(repeat_n(EMPTY, width - filled))
+                    .collect();
+
+                fragment.write().set(content(), bar);
+                ready(())
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+    use futures_signals::signal::Mutable;
+
+    use crate::app::{App, AppRef};
+
+    use super::*;
+
+    #[test]
+    fn progress_fills_proportionally() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        let progress = Mutable::new(2.0);
+        let mut fut = root.attach(ProgressBar::new(10, progress.signal()));
+        let id = fut.id();
+
+        let filled = |handle: &AppRef| {
+            handle
+                .world()
+                .get(id, content())
+                .unwrap()
+                .chars()
+                .filter(|&c| c == FILLED)
+                .count()
+        };
+
+        assert!((&mut fut).now_or_never().is_none());
+        assert_eq!(filled(&handle), 10);
+
+        progress.set(0.5);
+        assert!((&mut fut).now_or_never().is_none());
+        assert_eq!(filled(&handle), 5);
+        assert_eq!(
+            handle.world().get(id, content()).unwrap().chars().count(),
+            10
+        );
+    }
+}
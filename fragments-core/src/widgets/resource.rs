@@ -0,0 +1,105 @@
+use std::pin::pin;
+
+use async_trait::async_trait;
+use futures::{
+    future::{select, Either},
+    Future,
+};
+
+use crate::{app::Event, Fragment, Widget};
+
+/// Shows a placeholder until `data` resolves, and then mounts the widget built from it.
+///
+/// The placeholder is attached as a child and despawned once the data arrives. Despawning the
+/// resource before then cancels the data future.
+pub struct Resource<Fut, F, P> {
+    data: Fut,
+    build: F,
+    placeholder: P,
+}
+
+impl<Fut, F, P, W> Resource<Fut, F, P>
+where
+    Fut: Future,
+    F: FnOnce(Fut::Output) -> W,
+    W: Widget,
+    P: Widget<Output = ()>,
+{
+    pub fn new(data: Fut, build: F, placeholder: P) -> Self {
+        Self {
+            data,
+            build,
+            placeholder,
+        }
+    }
+}
+
+#[async_trait]
+impl<Fut, F, P, W> Widget for Resource<Fut, F, P>
+where
+    Fut: Future + Send,
+    Fut::Output: Send,
+    F: FnOnce(Fut::Output) -> W + Send,
+    W: Widget,
+    W::Output: Send + 'static,
+    P: Widget<Output = ()>,
+{
+    type Output = W::Output;
+
+    async fn mount(self, mut fragment: Fragment) -> W::Output {
+        let placeholder = fragment.attach(self.placeholder);
+        let placeholder_id = placeholder.id();
+
+        let data = match select(pin!(self.data), placeholder).await {
+            // Dropping the unfinished placeholder despawns it
+            Either::Left((data, _)) => data,
+            Either::Right((_, data)) => {
+                let data = data.await;
+                fragment.app().enqueue(Event::Despawn(placeholder_id)).ok();
+                data
+            }
+        };
+
+        fragment.put((self.build)(data)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{app::App, components::content, fragment::children_of, widgets::Text};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn placeholder_replaced_by_data() {
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        let resource = root.attach(Resource::new(
+            rx,
+            |data: Result<String, _>| Text::new(data.unwrap()),
+            Text::new("Loading"),
+        ));
+        let id = resource.id();
+        let resource = tokio::spawn(resource);
+
+        tokio::task::yield_now().await;
+        let placeholder = children_of(&handle.world(), id);
+        assert_eq!(placeholder.len(), 1);
+        assert_eq!(
+            handle.world().get(placeholder[0], content()).as_deref(),
+            Ok(&"Loading".into())
+        );
+
+        tx.send("Loaded".into()).unwrap();
+        resource.await.unwrap();
+        tokio::task::yield_now().await;
+
+        let world = handle.world();
+        assert!(!world.is_alive(placeholder[0]));
+        assert_eq!(world.get(id, content()).as_deref(), Ok(&"Loaded".into()));
+    }
+}
@@ -0,0 +1,144 @@
+use async_trait::async_trait;
+
+use crate::{layout::Axis, widgets::Stack, Fragment, Widget, WidgetCollection};
+
+/// Lays out its children next to each other horizontally.
+///
+/// Children are packed from the left, or from the right when the inherited
+/// [`direction`](crate::components::direction) is [`Direction::Rtl`](crate::layout::Direction::Rtl).
+/// The row sizes itself to fit its children.
+pub struct Row<W> {
+    widgets: W,
+    padding: f32,
+}
+
+impl<W: WidgetCollection> Row<W> {
+    pub fn new(widgets: W) -> Self {
+        Self {
+            widgets,
+            padding: 0.0,
+        }
+    }
+
+    /// Sets the space between children
+    pub fn with_padding(mut self, padding: f32) -> Self {
+        self.padding = padding;
+        self
+    }
+}
+
+#[async_trait]
+impl<W: WidgetCollection + Send> Widget for Row<W> {
+    type Output = ();
+
+    async fn mount(self, frag: Fragment) {
+        Stack::new(Axis::Horizontal, self.widgets)
+            .with_padding(self.padding)
+            .mount(frag)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec2, Vec2};
+
+    use crate::{
+        app::App,
+        components::{direction, measure as measure_component, position, size},
+        layout::Direction,
+        widgets::Text,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn rtl_packs_from_right() {
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        root.write().set(direction(), Direction::Rtl);
+
+        let row = root.attach(Row::new((Text::new("ab"), Text::new("cde"))).with_padding(1.0));
+        let row_id = row.id();
+        tokio::spawn(row);
+        tokio::task::yield_now().await;
+
+        let world = handle.world();
+        let children = crate::fragment::children_of(&world, row_id);
+        let pos = |i: usize| *world.get(children[i], position()).unwrap();
+
+        assert_eq!(*world.get(row_id, size()).unwrap(), vec2(6.0, 1.0));
+        assert_eq!(pos(0), vec2(4.0, 0.0));
+        assert_eq!(pos(1), vec2(0.0, 0.0));
+    }
+
+    #[tokio::test]
+    async fn row_of_eight_widget_types() {
+        struct Fixed<const N: usize>;
+
+        #[async_trait]
+        impl<const N: usize> Widget for Fixed<N> {
+            type Output = ();
+
+            async fn mount(self, mut fragment: Fragment) {
+                fragment.write().set(size(), vec2(N as f32, 1.0));
+            }
+        }
+
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let row = root.attach(Row::new((
+            Fixed::<1>, Fixed::<2>, Fixed::<3>, Fixed::<4>, Fixed::<5>, Fixed::<6>, Fixed::<7>,
+            Fixed::<8>,
+        )));
+        let row_id = row.id();
+        tokio::spawn(row);
+        tokio::task::yield_now().await;
+
+        assert_eq!(
+            *handle.world().get(row_id, size()).unwrap(),
+            vec2(36.0, 1.0)
+        );
+    }
+
+    #[tokio::test]
+    async fn unchanged_sizes_skip_layout() {
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let row = root.attach(Row::new((Text::new("ab"), Text::new("cde"))));
+        let row_id = row.id();
+        tokio::spawn(row);
+        tokio::task::yield_now().await;
+
+        let children = crate::fragment::children_of(&handle.world(), row_id);
+        let moved = vec2(10.0, 10.0);
+        {
+            let mut world = handle.world();
+            // Layout would put the child back at the origin
+            world.set(children[0], position(), moved).unwrap();
+            world.set(children[1], size(), vec2(3.0, 1.0)).unwrap();
+        }
+        tokio::task::yield_now().await;
+
+        assert_eq!(*handle.world().get(children[0], position()).unwrap(), moved);
+
+        {
+            // Measured sizes take precedence
+            let mut world = handle.world();
+            world.remove(children[1], measure_component()).unwrap();
+            world.set(children[1], size(), vec2(4.0, 1.0)).unwrap();
+        }
+        tokio::task::yield_now().await;
+
+        assert_eq!(
+            *handle.world().get(children[0], position()).unwrap(),
+            Vec2::ZERO
+        );
+    }
+}
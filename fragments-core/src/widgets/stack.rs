@@ -0,0 +1,314 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use flax::{
+    child_of, component,
+    events::{ChangeSubscriber, SubscriberFilterExt},
+};
+use futures::{join, stream::FuturesUnordered, StreamExt};
+use glam::Vec2;
+use tokio::sync::Notify;
+
+use crate::{
+    batch::Coalesced,
+    components::{constraints, direction, layout_hash, position, size},
+    layout::{direction_of, hash_layout, measure, Axis, Constraints, Direction},
+    Fragment, Widget, WidgetCollection,
+};
+
+component! {
+    /// Relation from a stack to itself, which narrows subscriptions down to the one stack
+    stack(id): (),
+}
+
+/// Lays out its children one after another along an [`Axis`].
+///
+/// Horizontally, children are packed from the left, or from the right when the inherited
+/// [`direction`] is [`Direction::Rtl`]. Vertically, they are packed from the top. The stack sizes
+/// itself to fit its children, and is relaid whenever the size of a child changes.
+///
+/// Children are offered unbounded space along the axis, and the stack's own [`constraints`]
+/// across it, so that a child such as [`Fill`](super::Fill) stretches across the stack, and
+/// [`Text`](super::Text) wraps at its width. Each child is then given the [`size`] it measured.
+///
+/// See [`Row`](super::Row) and [`Column`](super::Column) for the common cases.
+pub struct Stack<W> {
+    widgets: W,
+    axis: Axis,
+    padding: f32,
+}
+
+impl<W: WidgetCollection> Stack<W> {
+    pub fn new(axis: Axis, widgets: W) -> Self {
+        Self {
+            widgets,
+            axis,
+            padding: 0.0,
+        }
+    }
+
+    /// Sets the space between children
+    pub fn with_padding(mut self, padding: f32) -> Self {
+        self.padding = padding;
+        self
+    }
+}
+
+fn update_layout(frag: &mut Fragment, axis: Axis, padding: f32) {
+    let _span = tracing::debug_span!("layout").entered();
+
+    let id = frag.id();
+    let children = frag.children();
+
+    let mut frag = frag.write();
+    let world = frag.world_mut();
+
+    // Only a horizontal stack follows the direction of the text
+    let dir = match axis {
+        Axis::Horizontal => direction_of(world, id),
+        Axis::Vertical => Direction::Ltr,
+    };
+    // Pass the space across the axis on to the children
+    let own = world
+        .get(id, constraints())
+        .as_deref()
+        .copied()
+        .unwrap_or_default();
+    let offered = Constraints::new(Vec2::ZERO, axis.vec(f32::INFINITY, axis.across(own.max)));
+    for &child in &children {
+        // Setting the same value again would wake the child needlessly
+        if world.get(child, constraints()).ok().as_deref() != Some(&offered) {
+            world.set(child, constraints(), offered).unwrap();
+        }
+    }
+
+    let sizes = children
+        .into_iter()
+        .filter_map(|id| Some((id, measure(world, id, offered.max)?)))
+        .collect::<Vec<_>>();
+
+    // Give each child the size it measured, such as the wrapped size of text
+    for &(child, measured) in &sizes {
+        if world.get(child, size()).ok().as_deref() != Some(&measured) {
+            world.set(child, size(), measured).unwrap();
+        }
+    }
+
+    let hash = hash_layout(&sizes, (axis, padding.to_bits(), dir));
+    if world.get(id, layout_hash()).ok().as_deref() == Some(&hash) {
+        tracing::trace!(?id, "layout unchanged");
+        return;
+    }
+
+    let length = sizes.iter().map(|&(_, size)| axis.along(size)).sum::<f32>()
+        + padding * sizes.len().saturating_sub(1) as f32;
+    let thickness = sizes
+        .iter()
+        .map(|&(_, size)| axis.across(size))
+        .fold(0.0, f32::max);
+
+    let mut cursor = 0.0;
+    for (id, size) in sizes {
+        let along = match dir {
+            Direction::Ltr => cursor,
+            Direction::Rtl => length - cursor - axis.along(size),
+        };
+
+        world.set(id, position(), axis.vec(along, 0.0)).unwrap();
+        cursor += axis.along(size) + padding;
+    }
+
+    frag.set(size(), axis.vec(length, thickness))
+        .set(layout_hash(), hash);
+}
+
+#[async_trait]
+impl<W: WidgetCollection + Send> Widget for Stack<W> {
+    type Output = ();
+
+    async fn mount(self, mut frag: Fragment) {
+        let id = frag.id();
+        frag.write().set(stack(id), ());
+
+        let changed = Arc::new(Notify::new());
+        {
+            let mut world = frag.app().world();
+            world.subscribe(
+                ChangeSubscriber::new(&[size().key()], Coalesced::new(Arc::downgrade(&changed)))
+                    .filter(child_of(id).with()),
+            );
+            world.subscribe(
+                ChangeSubscriber::new(
+                    &[constraints().key()],
+                    Coalesced::new(Arc::downgrade(&changed)),
+                )
+                .filter(stack(id).with()),
+            );
+            // The direction is inherited from any ancestor
+            if self.axis == Axis::Horizontal {
+                world.subscribe(ChangeSubscriber::new(
+                    &[direction().key()],
+                    Coalesced::new(Arc::downgrade(&changed)),
+                ));
+            }
+        }
+
+        let mut children = self
+            .widgets
+            .attach(&mut frag)
+            .into_iter()
+            .collect::<FuturesUnordered<_>>();
+
+        let (axis, padding) = (self.axis, self.padding);
+        let update_children = async { while children.next().await.is_some() {} };
+        let layout = async {
+            loop {
+                update_layout(&mut frag, axis, padding);
+                changed.notified().await;
+            }
+        };
+
+        join!(update_children, layout);
+    }
+}
+
+/// Lays out its children below each other.
+///
+/// The column sizes itself to fit its children.
+pub struct Column<W> {
+    widgets: W,
+    padding: f32,
+}
+
+impl<W: WidgetCollection> Column<W> {
+    pub fn new(widgets: W) -> Self {
+        Self {
+            widgets,
+            padding: 0.0,
+        }
+    }
+
+    /// Sets the space between children
+    pub fn with_padding(mut self, padding: f32) -> Self {
+        self.padding = padding;
+        self
+    }
+}
+
+#[async_trait]
+impl<W: WidgetCollection + Send> Widget for Column<W> {
+    type Output = ();
+
+    async fn mount(self, frag: Fragment) {
+        Stack::new(Axis::Vertical, self.widgets)
+            .with_padding(self.padding)
+            .mount(frag)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{uvec2, vec2};
+
+    use crate::{
+        app::App,
+        canvas::{draw_widgets, Canvas},
+        widgets::{Fill, Row, Text},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn fill_spans_column() {
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let column = root.attach(Column::new((Text::new("ab"), Fill)));
+        let column_id = column.id();
+        handle
+            .world()
+            .set(
+                column_id,
+                constraints(),
+                Constraints::new(Vec2::ZERO, vec2(10.0, 5.0)),
+            )
+            .unwrap();
+        tokio::spawn(column);
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        let world = handle.world();
+        let fill = crate::fragment::children_of(&world, column_id)[1];
+        // Unbounded along the column, so only as tall as required
+        assert_eq!(
+            world.get(fill, constraints()).as_deref(),
+            Ok(&Constraints::new(Vec2::ZERO, vec2(10.0, f32::INFINITY)))
+        );
+        assert_eq!(*world.get(fill, size()).unwrap(), vec2(10.0, 0.0));
+        assert_eq!(*world.get(column_id, size()).unwrap(), vec2(10.0, 1.0));
+    }
+
+    #[tokio::test]
+    async fn text_wraps_in_narrow_column() {
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let column = root.attach(Column::new((Text::new("abcdef"), Text::new("g"))));
+        let column_id = column.id();
+        tokio::spawn(column);
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        assert_eq!(
+            *handle.world().get(column_id, size()).unwrap(),
+            vec2(6.0, 2.0)
+        );
+
+        // Narrowed once mounted, which relays the column
+        handle
+            .world()
+            .set(
+                column_id,
+                constraints(),
+                Constraints::new(Vec2::ZERO, vec2(4.0, f32::INFINITY)),
+            )
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        let world = handle.world();
+        let children = crate::fragment::children_of(&world, column_id);
+        assert_eq!(*world.get(column_id, size()).unwrap(), vec2(4.0, 3.0));
+        assert_eq!(*world.get(children[1], position()).unwrap(), vec2(0.0, 2.0));
+
+        // The text is drawn wrapped within the space it was given
+        let mut canvas = Canvas::new(uvec2(6, 3));
+        draw_widgets(&world, &mut canvas);
+        assert_eq!(canvas.to_string(), "abcd  \nef    \ng     ");
+    }
+
+    #[tokio::test]
+    async fn column_stacks_downwards() {
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        // Vertical stacks ignore the text direction
+        root.write().set(direction(), Direction::Rtl);
+
+        let column = root.attach(
+            Column::new((Text::new("ab"), Row::new((Text::new("c"), Text::new("de")))))
+                .with_padding(1.0),
+        );
+        let column_id = column.id();
+        tokio::spawn(column);
+        tokio::task::yield_now().await;
+
+        let world = handle.world();
+        let children = crate::fragment::children_of(&world, column_id);
+        let pos = |i: usize| *world.get(children[i], position()).unwrap();
+
+        assert_eq!(*world.get(column_id, size()).unwrap(), vec2(3.0, 3.0));
+        assert_eq!(pos(0), vec2(0.0, 0.0));
+        assert_eq!(pos(1), vec2(0.0, 2.0));
+    }
+}
@@ -0,0 +1,355 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use flax::{Entity, World};
+use futures::{
+    future::{ready, select, Either},
+    join, StreamExt,
+};
+use futures_signals::signal::{Mutable, SignalExt};
+use glam::{vec2, Vec2};
+
+use crate::{
+    components::{hidden, position, selected},
+    geometry::hit_region,
+    reconcile::{reconcile, KeyedOp},
+    terminal::on_mouse,
+    widgets::{Row, Text},
+    Fragment, Widget, WidgetFuture,
+};
+
+type TabFn = dyn Fn() -> Box<dyn Widget<Output = ()> + Send> + Send + Sync;
+
+/// What happens to the content of a tab when another tab is activated
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum InactiveTabs {
+    /// The content is [`hidden`] and no longer driven, but stays mounted along with its state
+    #[default]
+    Suspend,
+    /// The content is despawned, and mounted anew when the tab is activated again
+    Despawn,
+}
+
+/// A set of named tabs, of which only the active one is shown.
+///
+/// The titles are laid out in a tab bar, and clicking one activates its tab. The content of the
+/// active tab is placed below the bar, and is only mounted once its tab is first activated.
+///
+/// The index of the active tab is shared through a [`Mutable`], so that it can also be changed
+/// from elsewhere, e.g; a key binding.
+pub struct Tabs {
+    tabs: Vec<(String, Arc<TabFn>)>,
+    active: Mutable<usize>,
+    policy: InactiveTabs,
+}
+
+impl Tabs {
+    pub fn new(active: Mutable<usize>) -> Self {
+        Self {
+            tabs: Vec::new(),
+            active,
+            policy: InactiveTabs::default(),
+        }
+    }
+
+    /// Adds a tab, whose content is created by `content` each time it is mounted
+    pub fn with_tab<W>(
+        mut self,
+        title: impl Into<String>,
+        content: impl 'static + Fn() -> W + Send + Sync,
+    ) -> Self
+    where
+        W: 'static + Widget<Output = ()>,
+    {
+        let content: Arc<TabFn> = Arc::new(move || Box::new(content()));
+        self.tabs.push((title.into(), content));
+        self
+    }
+
+    /// Sets what happens to the content of inactive tabs
+    pub fn with_policy(mut self, policy: InactiveTabs) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+#[async_trait]
+impl Widget for Tabs {
+    type Output = ();
+
+    async fn mount(self, mut fragment: Fragment) {
+        let labels = self
+            .tabs
+            .iter()
+            .enumerate()
+            .map(|(index, (title, _))| {
+                Box::new(TabLabel {
+                    title: title.clone(),
+                    index,
+                    active: self.active.clone(),
+                }) as Box<dyn Widget<Output = ()> + Send>
+            })
+            .collect::<Vec<_>>();
+
+        let bar = fragment.attach(Row::new(labels).with_padding(1.0));
+        let panels = fragment.attach(Panels {
+            contents: self.tabs.into_iter().map(|(_, content)| content).collect(),
+            active: self.active,
+            policy: self.policy,
+        });
+
+        join!(bar, panels);
+    }
+}
+
+/// The title of a tab in the tab bar, which is [`selected`] while the tab is active
+struct TabLabel {
+    title: String,
+    index: usize,
+    active: Mutable<usize>,
+}
+
+#[async_trait]
+impl Widget for TabLabel {
+    type Output = ();
+
+    async fn mount(self, mut fragment: Fragment) {
+        let index = self.index;
+        let active = self.active.clone();
+
+        fragment.put_sync(Text::new(self.title));
+        fragment
+            .write()
+            .on_event(on_mouse(), move |id, world: &World, event: &MouseEvent| {
+                let point = vec2(event.column as f32, event.row as f32);
+                if event.kind == MouseEventKind::Down(MouseButton::Left)
+                    && hit_region(world, id).is_some_and(|rect| rect.contains(point))
+                {
+                    active.set_neq(index);
+                }
+            });
+
+        self.active
+            .signal()
+            .for_each(|current| {
+                let mut fragment = fragment.write();
+                if current == index {
+                    fragment.set(selected(), ());
+                } else {
+                    fragment.remove(selected());
+                }
+
+                ready(())
+            })
+            .await
+    }
+}
+
+/// Mounts the content of a tab when the tab is activated
+struct TabContent(Arc<TabFn>);
+
+#[async_trait]
+impl Widget for TabContent {
+    type Output = ();
+
+    async fn mount(self, fragment: Fragment) {
+        (self.0)().mount(fragment).await
+    }
+}
+
+/// Holds the content of the tabs, keyed by the index of their tab
+struct Panels {
+    contents: Vec<Arc<TabFn>>,
+    active: Mutable<usize>,
+    policy: InactiveTabs,
+}
+
+#[async_trait]
+impl Widget for Panels {
+    type Output = ();
+
+    async fn mount(self, mut fragment: Fragment) {
+        fragment.write().set(position(), vec2(0.0, 1.0));
+
+        let mut changes = self.active.signal().to_stream();
+        let mut children: Vec<(usize, Entity)> = Vec::new();
+        // Content which has not completed yet
+        let mut running: HashMap<usize, WidgetFuture<'static>> = HashMap::new();
+        let mut current = None;
+
+        loop {
+            // Only the content of the active tab is driven
+            let next = match current.and_then(|index| running.get_mut(&index)) {
+                Some(content) => match select(changes.next(), content).await {
+                    Either::Left((next, _)) => next,
+                    Either::Right(((), _)) => {
+                        running.remove(&current.unwrap());
+                        continue;
+                    }
+                },
+                None => changes.next().await,
+            };
+
+            let Some(index) = next else { break };
+            if index >= self.contents.len() {
+                tracing::warn!(index, "Tab index out of range");
+                continue;
+            }
+
+            let mut new_keys = match self.policy {
+                InactiveTabs::Suspend => children.iter().map(|&(key, _)| key).collect(),
+                InactiveTabs::Despawn => Vec::new(),
+            };
+            if !new_keys.contains(&index) {
+                new_keys.push(index);
+                new_keys.sort_unstable();
+            }
+
+            let widgets = new_keys
+                .iter()
+                .map(|&key| (key, TabContent(self.contents[key].clone())))
+                .collect();
+            let reconciled = reconcile(&mut fragment, &children, widgets);
+
+            for op in reconciled.ops {
+                if let KeyedOp::Despawn(key) = op {
+                    running.remove(&key);
+                }
+            }
+
+            {
+                let mut world = fragment.app().world();
+                for (key, content) in reconciled.spawned {
+                    world.set(content.id(), position(), Vec2::ZERO).unwrap();
+                    running.insert(key, content);
+                }
+
+                for &(key, id) in &reconciled.children {
+                    if key == index {
+                        world.remove(id, hidden()).ok();
+                    } else {
+                        world.set(id, hidden(), ()).unwrap();
+                    }
+                }
+            }
+
+            children = reconciled.children;
+            current = Some(index);
+        }
+
+        // The active index can no longer change
+        if let Some(content) = current.and_then(|index| running.remove(&index)) {
+            content.await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crossterm::event::{KeyModifiers, MouseEvent};
+    use glam::uvec2;
+
+    use crate::{
+        app::App,
+        canvas::{draw_widgets, Canvas},
+        components::content,
+        events::send_event,
+        fragment::children_of,
+    };
+
+    use super::*;
+
+    /// Counts its mounts, and keeps a subtree of its own
+    struct Page {
+        text: &'static str,
+        mounts: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Widget for Page {
+        type Output = ();
+
+        async fn mount(self, mut fragment: Fragment) {
+            self.mounts.fetch_add(1, Ordering::Relaxed);
+            fragment.attach(Row::new((Text::new(self.text),))).await
+        }
+    }
+
+    #[tokio::test]
+    async fn inactive_tab_is_suspended() {
+        let (handle, events) = App::new().start();
+        tokio::spawn(events);
+
+        let mounts = Arc::new(AtomicUsize::new(0));
+        let page = |text| {
+            let mounts = mounts.clone();
+            move || Page {
+                text,
+                mounts: mounts.clone(),
+            }
+        };
+
+        let active = Mutable::new(0);
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        let tabs = root.attach(
+            Tabs::new(active.clone())
+                .with_tab("One", page("first"))
+                .with_tab("Two", page("second")),
+        );
+        let tabs_id = tabs.id();
+        tokio::spawn(tabs);
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        let render = || {
+            let mut target = Canvas::new(uvec2(10, 2));
+            draw_widgets(&handle.world(), &mut target);
+            target.rows().collect::<Vec<_>>()
+        };
+        let panels = children_of(&handle.world(), tabs_id)[1];
+
+        assert_eq!(render(), ["One Two   ", "first     "]);
+        let first = children_of(&handle.world(), panels);
+        assert_eq!(first.len(), 1);
+        let first_text = {
+            let world = handle.world();
+            children_of(&world, children_of(&world, first[0])[0])[0]
+        };
+
+        active.set(1);
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        assert_eq!(render(), ["One Two   ", "second    "]);
+        {
+            let world = handle.world();
+            let contents = children_of(&world, panels);
+            assert_eq!(contents.len(), 2);
+            assert_eq!(contents[0], first[0]);
+            assert!(world.has(first[0], hidden()));
+            assert_eq!(
+                world.get(first_text, content()).as_deref(),
+                Ok(&"first".into())
+            );
+        }
+
+        // Clicking the first title switches back
+        send_event(
+            &handle.world(),
+            on_mouse(),
+            MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column: 1,
+                row: 0,
+                modifiers: KeyModifiers::NONE,
+            },
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        assert_eq!(active.get(), 0);
+        assert_eq!(render(), ["One Two   ", "first     "]);
+        assert!(handle.world().is_alive(first_text));
+        assert_eq!(mounts.load(Ordering::Relaxed), 2);
+    }
+}
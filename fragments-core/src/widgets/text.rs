@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use glam::{vec2, Vec2};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    accessibility::Role,
+    components::{content, measure, rich_content, role, size},
+    layout::Measure,
+    style::{RichContent, Span},
+    Fragment, FragmentRef, SyncWidget, Widget,
+};
+
+/// Text sized to fit its content, in terminal cells.
+///
+/// The text is split into lines on newlines, where a trailing newline does not start another
+/// line. The width is that of the widest line, counting wide characters such as CJK as two
+/// cells, and the height is the number of lines. Empty text still takes up one line.
+///
+/// When measured with less width than a line needs, the line wraps onto more lines.
+pub struct Text {
+    content: RichContent,
+}
+
+impl Text {
+    /// Creates unstyled text
+    pub fn new(text: impl Into<String>) -> Self {
+        Self::rich([Span::from(text.into())])
+    }
+
+    /// Creates text made up of individually styled spans
+    pub fn rich(spans: impl IntoIterator<Item = Span>) -> Self {
+        Self {
+            content: spans.into_iter().collect(),
+        }
+    }
+}
+
+/// Returns the width in cells of each line of `text`
+fn line_widths(text: &str) -> Vec<usize> {
+    let widths = text.lines().map(|line| line.width()).collect::<Vec<_>>();
+    if widths.is_empty() {
+        vec![0]
+    } else {
+        widths
+    }
+}
+
+/// Size of lines of the given widths, each wrapped at the available width
+fn wrapped_size(lines: &[usize], available: Vec2) -> Vec2 {
+    let widest = lines.iter().copied().max().unwrap_or_default() as f32;
+    let width = widest.min(available.x.floor().max(1.0));
+    let height = lines
+        .iter()
+        .map(|&len| (len as f32 / width).ceil().max(1.0))
+        .sum();
+
+    vec2(width, height)
+}
+
+impl Measure for Text {
+    fn measure(&self, available: Vec2) -> Vec2 {
+        wrapped_size(&line_widths(&self.content.plain()), available)
+    }
+}
+
+impl SyncWidget for Text {
+    fn mount_sync(self, fragment: &mut FragmentRef) {
+        let plain = self.content.plain();
+        let lines = line_widths(&plain);
+
+        fragment
+            .set(size(), wrapped_size(&lines, Vec2::splat(f32::INFINITY)))
+            .set(
+                measure(),
+                Arc::new(move |available| wrapped_size(&lines, available)),
+            )
+            .set(role(), Role::Label);
+
+        // Plain text is kept in `content` so it is readable without caring about styles
+        if self
+            .content
+            .0
+            .iter()
+            .all(|span| span.style == Default::default())
+        {
+            fragment.set(content(), plain).remove(rich_content());
+        } else {
+            fragment.set(rich_content(), self.content).remove(content());
+        }
+    }
+}
+
+#[async_trait]
+impl Widget for Text {
+    type Output = ();
+
+    async fn mount(self, mut fragment: Fragment) {
+        fragment.put_sync(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::App;
+
+    use super::*;
+
+    #[test]
+    fn put_sync_without_runtime() {
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+
+        root.put_sync(Text::new("Hello"));
+
+        let world = handle.world();
+        assert_eq!(
+            world.get(root.id(), content()).as_deref(),
+            Ok(&"Hello".into())
+        );
+        assert_eq!(*world.get(root.id(), size()).unwrap(), vec2(5.0, 1.0));
+    }
+
+    #[test]
+    fn multiline_size_in_cells() {
+        let size_of = |text: &str| Text::new(text).measure(Vec2::splat(f32::INFINITY));
+
+        assert_eq!(size_of(""), vec2(0.0, 1.0));
+        assert_eq!(size_of("ab\ncdef\n"), vec2(4.0, 2.0));
+        assert_eq!(size_of("ab\n\nc"), vec2(2.0, 3.0));
+        // Wide characters take up two cells each
+        assert_eq!(size_of("日本"), vec2(4.0, 1.0));
+
+        // Each line wraps on its own
+        assert_eq!(Text::new("abc\nd").measure(vec2(2.0, 10.0)), vec2(2.0, 3.0));
+    }
+
+    #[test]
+    fn narrow_width_wraps() {
+        let text = Text::new("Hello, World");
+
+        assert_eq!(text.measure(Vec2::splat(f32::INFINITY)), vec2(12.0, 1.0));
+        assert_eq!(text.measure(vec2(5.0, 10.0)), vec2(5.0, 3.0));
+
+        let app = App::new();
+        let handle = app.handle();
+        let mut root = Fragment::spawn(&mut handle.world(), handle.clone(), None);
+        root.put_sync(text);
+
+        let world = handle.world();
+        assert_eq!(
+            crate::layout::measure(&world, root.id(), vec2(6.0, 10.0)),
+            Some(vec2(6.0, 2.0))
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use crossterm::event::{KeyCode, KeyEvent};
+use flax::{Entity, World};
+use glam::vec2;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    accessibility::Role,
+    components::{content, cursor_pos, cursor_visible, focusable, focused, role, size, validation},
+    terminal::{on_char, on_key},
+    Fragment, WeakFragment, Widget,
+};
+
+/// A single line text field, edited while it has focus.
+///
+/// The terminal cursor is shown at the end of the text.
+pub struct TextInput {
+    text: String,
+    validator: Option<Arc<Validator>>,
+}
+
+type Validator = dyn Fn(&str) -> Result<(), String> + Send + Sync;
+
+impl TextInput {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            validator: None,
+        }
+    }
+
+    /// Checks the text after each edit, storing the outcome in the [`validation`] component
+    pub fn with_validator(
+        mut self,
+        validator: impl Fn(&str) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        self.validator = Some(Arc::new(validator));
+        self
+    }
+}
+
+/// Replaces the text of the input, moving the cursor to the end
+fn set_text(fragment: &WeakFragment, validator: Option<&Validator>, text: String) {
+    let len = text.width() as f32;
+    if let Some(validator) = validator {
+        fragment.upgrade_set(validation(), validator(&text));
+    }
+    fragment.upgrade_set(cursor_pos(), vec2(len, 0.0));
+    fragment.upgrade_set(size(), vec2(len + 1.0, 1.0));
+    fragment.upgrade_set(content(), text);
+}
+
+/// Returns the current text if the input has focus
+fn focused_text(world: &World, id: Entity) -> Option<String> {
+    if world.has(id, focused()) {
+        world.get(id, content()).ok().map(|text| text.clone())
+    } else {
+        None
+    }
+}
+
+#[async_trait]
+impl Widget for TextInput {
+    type Output = ();
+
+    async fn mount(self, mut fragment: Fragment) {
+        let len = self.text.width() as f32;
+        let weak = fragment.downgrade();
+        let weak_key = weak.clone();
+        let validator = self.validator.clone();
+        let validator_key = self.validator;
+
+        fragment
+            .write()
+            .set(size(), vec2(len + 1.0, 1.0))
+            .set(content(), self.text)
+            .set(role(), Role::TextInput)
+            .set(focusable(), ())
+            .set(cursor_visible(), true)
+            .set(cursor_pos(), vec2(len, 0.0))
+            .on_event(on_char(), move |id, world: &World, &c: &char| {
+                if let Some(mut text) = focused_text(world, id) {
+                    text.push(c);
+                    set_text(&weak, validator.as_deref(), text);
+                }
+            })
+            .on_event(on_key(), move |id, world: &World, key: &KeyEvent| {
+                if let KeyEvent {
+                    code: KeyCode::Backspace,
+                    ..
+                } = key
+                {
+                    if let Some(mut text) = focused_text(world, id) {
+                        text.pop();
+                        set_text(&weak_key, validator_key.as_deref(), text);
+                    }
+                }
+            });
+
+        futures::future::pending().await
+    }
+}
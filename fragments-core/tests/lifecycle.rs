@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use flax::{child_of, entity_ids, Query};
+use fragments_core::{app::App, components::content, Fragment, Widget};
+
+/// Sets its content and stays mounted, optionally with a child of its own
+struct Node {
+    label: &'static str,
+    depth: usize,
+}
+
+#[async_trait]
+impl Widget for Node {
+    type Output = ();
+
+    async fn mount(self, mut fragment: Fragment) {
+        fragment.write().set(content(), self.label.into());
+
+        if self.depth > 0 {
+            fragment
+                .attach(Node {
+                    label: self.label,
+                    depth: self.depth - 1,
+                })
+                .await
+        } else {
+            futures::future::pending().await
+        }
+    }
+}
+
+struct Root;
+
+#[async_trait]
+impl Widget for Root {
+    type Output = ();
+
+    async fn mount(self, mut fragment: Fragment) {
+        let mut dropped = fragment.attach(Node {
+            label: "dropped",
+            depth: 2,
+        });
+        let mut retained = fragment.attach(Node {
+            label: "retained",
+            depth: 1,
+        });
+
+        // Mount both subtrees
+        tokio::select! {
+            _ = &mut dropped => unreachable!(),
+            _ = &mut retained => unreachable!(),
+            _ = tokio::time::sleep(Duration::from_millis(10)) => {}
+        }
+
+        let subtree = |world: &flax::World, label: &str| {
+            Query::new((entity_ids(), content()))
+                .borrow(world)
+                .iter()
+                .filter(|(_, content)| *content == label)
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>()
+        };
+
+        let dropped_ids = subtree(&fragment.app().world(), "dropped");
+        assert_eq!(dropped_ids.len(), 3);
+        assert_eq!(subtree(&fragment.app().world(), "retained").len(), 2);
+
+        drop(dropped);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let world = fragment.app().world();
+        for id in dropped_ids {
+            assert!(!world.is_alive(id));
+        }
+
+        let retained_ids = subtree(&world, "retained");
+        assert_eq!(retained_ids.len(), 2);
+        assert!(retained_ids.iter().all(|&id| world.is_alive(id)));
+
+        let children = Query::new(entity_ids())
+            .with(child_of(fragment.id()))
+            .borrow(&world)
+            .iter()
+            .collect::<Vec<_>>();
+        assert_eq!(children, [retained.id()]);
+    }
+}
+
+#[tokio::test]
+async fn dropped_future_despawns_subtree() {
+    assert_eq!(App::new().run(Root).await.unwrap(), 0);
+}
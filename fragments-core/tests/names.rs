@@ -0,0 +1,24 @@
+use flax::components::name;
+use fragments_core::{testing::TestApp, widgets::Text, WidgetExt};
+use futures::FutureExt;
+
+#[test]
+fn id_of_resolves_named_children() {
+    let mut app = TestApp::new();
+
+    let title = app.root().attach(Text::new("Title").tap(|f| {
+        f.app().world().set(f.id(), name(), "title".into()).unwrap();
+    }));
+    let title_id = title.id();
+    title.now_or_never().unwrap();
+
+    let body = app.root().attach(Text::new("Body"));
+    let body_id = body.id();
+    body.now_or_never().unwrap();
+
+    assert_eq!(app.id_of("title"), Some(title_id));
+    assert_eq!(app.id_of("root/1"), Some(body_id));
+    assert_eq!(app.name_of(body_id).as_deref(), Some("root/1"));
+    assert_eq!(app.name_of(title_id).as_deref(), Some("title"));
+    assert_eq!(app.id_of("root/2"), None);
+}
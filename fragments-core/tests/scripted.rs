@@ -0,0 +1,61 @@
+use async_trait::async_trait;
+use flax::{component, Query, World};
+use fragments_core::{
+    app::Event,
+    components::content,
+    events::EventHooks,
+    testing::{run_scripted, ScriptExit, ScriptedEvent},
+    Fragment, Widget,
+};
+
+component! {
+    on_key: EventHooks<char>,
+}
+
+/// Mirrors the example's `EventHandler`, quitting on `q`
+struct EventHandler;
+
+#[async_trait]
+impl Widget for EventHandler {
+    type Output = ();
+
+    async fn mount(self, mut fragment: Fragment) {
+        let app = fragment.app().clone();
+        fragment.write().set(content(), String::new()).on_event(
+            on_key(),
+            move |_, _: &World, &key: &char| {
+                if key == 'q' {
+                    app.enqueue(Event::Exit).unwrap();
+                }
+            },
+        );
+
+        futures::future::pending().await
+    }
+}
+
+#[tokio::test]
+async fn quit_on_keypress() {
+    let output = run_scripted(
+        EventHandler,
+        vec![
+            ScriptedEvent::new(on_key(), 'a'),
+            ScriptedEvent::new(on_key(), 'q'),
+        ],
+        16,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(output.exit, ScriptExit::Exited);
+}
+
+#[tokio::test]
+async fn exhausted_without_exit() {
+    let output = run_scripted(EventHandler, vec![ScriptedEvent::new(on_key(), 'a')], 16)
+        .await
+        .unwrap();
+
+    assert_eq!(output.exit, ScriptExit::Exhausted);
+    assert_eq!(Query::new(content()).borrow(&output.world()).count(), 1);
+}
@@ -1,23 +1,23 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use flax::World;
 use fragments_core::{
     app::{self, App},
-    components,
-    events::{send_event, EventHook},
+    events::{send_event, EventHooks},
     Widget,
 };
-use futures::future::BoxFuture;
-use futures_signals::signal::{Mutable, SignalExt};
+use futures_signals::signal::Mutable;
 use tracing_subscriber::{prelude::*, Registry};
 use tracing_tree::HierarchicalLayer;
 use winit::{
     dpi::PhysicalSize,
     event::{Event, KeyboardInput, WindowEvent},
-    event_loop::{EventLoop, EventLoopBuilder},
+    event_loop::EventLoop,
     window::{Window, WindowBuilder, WindowId},
 };
 
+#[allow(dead_code)]
 struct GraphicsState {
     surface: wgpu::Surface,
     device: wgpu::Device,
@@ -26,6 +26,7 @@ struct GraphicsState {
     size: winit::dpi::PhysicalSize<u32>,
 }
 
+#[allow(dead_code)]
 impl GraphicsState {
     // Creates a new graphics state
     async fn new(window: &Window) -> Self {
@@ -97,7 +98,7 @@ impl GraphicsState {
         }
     }
 
-    fn on_event(&mut self, event: &WindowEvent) -> bool {
+    fn on_event(&mut self, _event: &WindowEvent) -> bool {
         todo!()
     }
 
@@ -124,14 +125,20 @@ impl Widget for GraphicsLayer {
 
         fragment
             .write()
-            .on_event(on_resize(), move |_, _, new_size: &PhysicalSize<u32>| {
-                tracing::info!("Resizing: {new_size:?}");
-                state.lock_mut().on_resize(*new_size);
-            })
-            .on_event(on_keyboard_input(), move |_, _, input| {
-                tracing::info!(?input, "Input");
-            })
-            .on_event(on_char_typed(), move |_, _, c| {
+            .on_event(
+                on_resize(),
+                move |_, _: &World, new_size: &PhysicalSize<u32>| {
+                    tracing::info!("Resizing: {new_size:?}");
+                    state.lock_mut().on_resize(*new_size);
+                },
+            )
+            .on_event(
+                on_keyboard_input(),
+                move |_, _: &World, input: &KeyboardInput| {
+                    tracing::info!(?input, "Input");
+                },
+            )
+            .on_event(on_char_typed(), move |_, _: &World, c: &char| {
                 tracing::info!(?c, "Character");
             });
 
@@ -144,10 +151,10 @@ struct WindowLayer {
 }
 
 flax::component! {
-    on_keyboard_input: EventHook<KeyboardInput>,
-    on_char_typed: EventHook<char>,
-    on_window_close: EventHook<WindowId>,
-    on_resize: EventHook<PhysicalSize<u32>>,
+    on_keyboard_input: EventHooks<KeyboardInput>,
+    on_char_typed: EventHooks<char>,
+    on_window_close: EventHooks<WindowId>,
+    on_resize: EventHooks<PhysicalSize<u32>>,
 
     graphics_state: GraphicsState,
 
@@ -169,30 +176,29 @@ impl Widget for WindowLayer {
         events.run(move |event, _, ctl| {
             let _window = &window;
 
-            match event {
-                Event::WindowEvent { window_id, event } => match event {
+            if let Event::WindowEvent { event, .. } = event {
+                match event {
                     winit::event::WindowEvent::CloseRequested => {
                         app.enqueue(app::Event::Exit).ok();
                         ctl.set_exit();
                     }
                     WindowEvent::Resized(new_size) => {
-                        send_event(&app.world(), on_resize(), new_size)
+                        send_event(&app.world(), on_resize(), new_size);
                     }
                     WindowEvent::KeyboardInput { input, .. } => {
-                        send_event(&app.world(), on_keyboard_input(), input)
+                        send_event(&app.world(), on_keyboard_input(), input);
                     }
                     WindowEvent::ReceivedCharacter(c) => {
-                        send_event(&app.world(), on_char_typed(), c)
+                        send_event(&app.world(), on_char_typed(), c);
                     }
                     _ => {}
-                },
-                _ => {}
+                }
             }
         });
     }
 }
 
-fn application() -> impl Widget {
+fn application() -> impl Widget<Output = eyre::Result<()>> {
     WindowLayer {
         title: "Fragments".into(),
     }
@@ -204,6 +210,6 @@ async fn main() -> eyre::Result<()> {
     tracing::subscriber::set_global_default(subscriber).unwrap();
     tracing::info!("Starting");
 
-    App::new().run(application()).await;
-    Ok(())
+    let code = App::new().run(application()).await?;
+    std::process::exit(code)
 }